@@ -0,0 +1,136 @@
+use drag_controller::{Drag, DragController};
+use piston_window::*;
+
+const MAX_ZOOM : f64 = 20.0;
+/// Zoom factor applied by one notch of the scroll wheel.
+const ZOOM_STEP : f64 = 1.2;
+
+/// Largest window opened for a world, bigger worlds are zoomed or panned.
+pub const MAX_WINDOW_SIZE : f64 = 1000.0;
+/// Smallest window, leaving room for the overlays.
+pub const MIN_WINDOW_SIZE : f64 = 400.0;
+
+/// Window size in pixels fitting `world_size`, within `MIN_WINDOW_SIZE` and
+/// `MAX_WINDOW_SIZE`.
+pub fn window_size(world_size : [f64; 2]) -> [u32; 2] {
+    [world_size[0].clamp(MIN_WINDOW_SIZE, MAX_WINDOW_SIZE) as u32, world_size[1].clamp(MIN_WINDOW_SIZE, MAX_WINDOW_SIZE) as u32]
+}
+
+/// 2D view over a world : dragging with the left button pans, the scroll
+/// wheel zooms around the cursor. The map never zooms out past the size
+/// fitting the window, and is kept in view.
+pub struct Camera {
+    /// Screen position in pixels of the world origin.
+    pub offset : [f64; 2],
+    pub zoom : f64,
+    /// Size in pixels of the map at zoom 1.
+    map : [f64; 2],
+    /// Size of the window.
+    view : [f64; 2],
+    drag : DragController,
+    // Cursor position at the last drag event.
+    dragged_from : [f64; 2],
+    cursor : [f64; 2]
+}
+
+impl Camera {
+    /// Camera over a map of `map` pixels, seen through a window of `view` pixels.
+    pub fn new(map : [f64; 2], view : [f64; 2]) -> Camera {
+        let mut camera = Camera { offset : [0.0; 2], zoom : 1.0, map, view, drag : DragController::new(), dragged_from : [0.0; 2], cursor : [0.0; 2] };
+        camera.reset();
+        camera
+    }
+
+    pub fn reset(&mut self) {
+        self.offset = [0.0; 2];
+        self.zoom = 1.0_f64.clamp(self.min_zoom(), MAX_ZOOM);
+        self.clamp();
+    }
+
+    /// Zoom at which the whole map fits the window.
+    pub fn min_zoom(&self) -> f64 {
+        (self.view[0] / self.map[0]).min(self.view[1] / self.map[1]).min(MAX_ZOOM)
+    }
+
+    /// Zooms by `factor`, keeping the point under `anchor` in place.
+    pub fn zoom_at(&mut self, anchor : [f64; 2], factor : f64) {
+        let zoom = (self.zoom * factor).clamp(self.min_zoom(), MAX_ZOOM);
+        let ratio = zoom / self.zoom;
+        for (offset, anchor) in self.offset.iter_mut().zip(anchor) {
+            *offset = anchor - (anchor - *offset) * ratio;
+        }
+        self.zoom = zoom;
+        self.clamp();
+    }
+
+    /// Keeps the map in view : centred along the sides where it is smaller
+    /// than the window, covering the window along the others.
+    fn clamp(&mut self) {
+        for axis in 0..2 {
+            let size = self.map[axis] * self.zoom;
+            self.offset[axis] = if size <= self.view[axis] {
+                (self.view[axis] - size) / 2.0
+            } else {
+                self.offset[axis].clamp(self.view[axis] - size, 0.0)
+            };
+        }
+    }
+
+    pub fn event(&mut self, e : &Event) {
+        self.resize_event(e);
+        self.pan_event(e);
+        self.zoom_event(e);
+    }
+
+    /// Follows the size of the window.
+    pub fn resize_event(&mut self, e : &Event) {
+        if let Some(args) = e.resize_args() {
+            self.view = args.window_size;
+            self.zoom = self.zoom.clamp(self.min_zoom(), MAX_ZOOM);
+            self.clamp();
+        }
+    }
+
+    /// Pans the view on left button drags.
+    pub fn pan_event(&mut self, e : &Event) {
+        let (offset, dragged_from) = (&mut self.offset, &mut self.dragged_from);
+        self.drag.event(e, |drag| {
+            match drag {
+                Drag::Start(x, y) => *dragged_from = [x, y],
+                Drag::Move(x, y) => {
+                    offset[0] += x - dragged_from[0];
+                    offset[1] += y - dragged_from[1];
+                    *dragged_from = [x, y];
+                },
+                Drag::End(..) | Drag::Interrupt => ()
+            }
+            true
+        });
+        self.clamp();
+    }
+
+    /// Zooms on the scroll wheel, around the cursor.
+    pub fn zoom_event(&mut self, e : &Event) {
+        if let Some(cursor) = e.mouse_cursor_args() {
+            self.cursor = cursor;
+        }
+        if let Some([_, scroll]) = e.mouse_scroll_args() {
+            self.zoom_at(self.cursor, ZOOM_STEP.powf(scroll));
+        }
+    }
+
+    /// Whether the left button is dragging the view.
+    pub fn dragging(&self) -> bool {
+        self.drag.drag
+    }
+
+    /// `c` moved to the camera's point of view.
+    pub fn transform(&self, c : Context) -> Context {
+        c.trans(self.offset[0], self.offset[1]).zoom(self.zoom)
+    }
+
+    /// World position in pixels under the screen position `screen`.
+    pub fn to_world(&self, screen : [f64; 2]) -> [f64; 2] {
+        [(screen[0] - self.offset[0]) / self.zoom, (screen[1] - self.offset[1]) / self.zoom]
+    }
+}
@@ -0,0 +1,203 @@
+use crate::display::hud::{BOX_COLOR, FONT_SIZE, TEXT_COLOR};
+use crate::simulation::statistics::{StatisticsCollector, TickStats};
+
+use piston_window::*;
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+pub const PANEL_WIDTH : f64 = 260.0;
+/// Number of ticks the charts span.
+pub const CHART_HISTORY : usize = 500;
+/// Number of ticks the reward is averaged over.
+const REWARD_WINDOW : usize = 50;
+const MARGIN : f64 = 8.0;
+const TITLE_HEIGHT : f64 = 16.0;
+const PLOT_COLOR : [f32; 4] = [0.0, 0.0, 0.0, 0.4];
+const AXIS_COLOR : [f32; 4] = [1.0, 1.0, 1.0, 0.5];
+// Thirst and hunger in the colours of the humans.
+const THIRST_COLOR : [f32; 4] = [1.0, 0.4, 0.3, 1.0];
+const HUNGER_COLOR : [f32; 4] = [0.4, 0.6, 1.0, 1.0];
+const WATER_COLOR : [f32; 4] = [0.5, 0.7, 1.0, 1.0];
+const FOOD_COLOR : [f32; 4] = [0.4, 0.9, 0.4, 1.0];
+
+/// Values of one statistic, NaN where it is unknown.
+struct Line {
+    name : &'static str,
+    color : [f32; 4],
+    values : Vec<f64>
+}
+
+impl Line {
+    fn new<F : Fn(&TickStats) -> f64>(name : &'static str, color : [f32; 4], history : &VecDeque<TickStats>, value : F) -> Line {
+        Line { name, color, values : history.iter().map(value).collect() }
+    }
+
+    fn latest(&self) -> Option<f64> {
+        self.values.last().copied().filter(|value| value.is_finite())
+    }
+}
+
+/// Lines sharing a vertical range, bounds left `None` follow the values.
+struct Chart {
+    title : &'static str,
+    lines : Vec<Line>,
+    min : Option<f64>,
+    max : Option<f64>
+}
+
+impl Chart {
+    fn range(&self) -> Option<[f64; 2]> {
+        let values = || self.lines.iter().flat_map(|line| line.values.iter()).copied().filter(|value| value.is_finite());
+        let min = self.min.or_else(|| values().reduce(f64::min))?;
+        let max = self.max.or_else(|| values().reduce(f64::max))?;
+        // A flat line is drawn in the middle.
+        Some(if max - min < 1e-9 { [min - 1.0, max + 1.0] } else { [min, max] })
+    }
+}
+
+/// Mean of the known rewards of the last `REWARD_WINDOW` ticks at each tick.
+fn reward_line(history : &VecDeque<TickStats>) -> Line {
+    let rewards : Vec<Option<f64>> = history.iter().map(|stats| stats.reward).collect();
+    let values = (0..rewards.len()).map(|end| {
+        let known : Vec<f64> = rewards[end.saturating_sub(REWARD_WINDOW - 1)..=end].iter().flatten().copied().collect();
+        if known.is_empty() { f64::NAN } else { known.iter().sum::<f64>() / known.len() as f64 }
+    }).collect();
+    Line { name : "reward", color : TEXT_COLOR, values }
+}
+
+fn charts(history : &VecDeque<TickStats>) -> Vec<Chart> {
+    vec![
+        Chart {
+            title : "population",
+            lines : vec![Line::new("alive", TEXT_COLOR, history, |stats| stats.alive as f64)],
+            min : Some(0.0),
+            max : None
+        },
+        Chart {
+            title : "needs",
+            lines : vec![
+                Line::new("thirst", THIRST_COLOR, history, |stats| stats.thirst.mean),
+                Line::new("hunger", HUNGER_COLOR, history, |stats| stats.hunger.mean)
+            ],
+            min : Some(0.0),
+            max : Some(100.0)
+        },
+        Chart {
+            title : "resources",
+            lines : vec![
+                Line::new("water", WATER_COLOR, history, |stats| stats.water),
+                Line::new("food", FOOD_COLOR, history, |stats| stats.food)
+            ],
+            min : Some(0.0),
+            max : None
+        },
+        Chart {
+            title : "learning",
+            lines : vec![reward_line(history)],
+            min : None,
+            max : None
+        }
+    ]
+}
+
+fn label(value : f64) -> String {
+    if value.fract() == 0.0 { format!("{value:.0}") } else { format!("{value:.1}") }
+}
+
+/// Side panel of rolling charts over the last `CHART_HISTORY` ticks : living
+/// humans, mean thirst and hunger, water and food left, and the reward of
+/// online learning averaged over the last `REWARD_WINDOW` ticks. C shows or
+/// hides it.
+pub struct ChartPanel {
+    pub visible : bool,
+    statistics : Arc<Mutex<StatisticsCollector>>,
+    charts : Vec<Chart>,
+    // Tick of the statistics the charts were built from.
+    tick : Option<u64>
+}
+
+impl ChartPanel {
+    /// Panel drawing the history of `statistics`, sampled by the simulation.
+    pub fn new(statistics : Arc<Mutex<StatisticsCollector>>) -> ChartPanel {
+        ChartPanel { visible : false, statistics, charts : Vec::new(), tick : None }
+    }
+
+    /// Applies the chart key `key`, returns whether it was one.
+    pub fn key(&mut self, key : Key) -> bool {
+        if key != Key::C {
+            return false;
+        }
+        self.visible = !self.visible;
+        true
+    }
+
+    /// Rebuilds the charts if the statistics moved on while they are shown.
+    pub fn update(&mut self) {
+        if !self.visible {
+            return;
+        }
+        let statistics = self.statistics.lock().unwrap();
+        let tick = statistics.latest().map(|stats| stats.tick);
+        if tick != self.tick {
+            self.tick = tick;
+            self.charts = charts(&statistics.history);
+        }
+    }
+
+    /// Draws the panel along the right edge of the window.
+    pub fn draw(&self, glyphs : &mut Glyphs, c : Context, g : &mut G2d) {
+        if !self.visible {
+            return;
+        }
+        let [window_width, window_height] = c.get_view_size();
+        let left = window_width - PANEL_WIDTH;
+        rectangle(BOX_COLOR, [left, 0.0, PANEL_WIDTH, window_height], c.transform, g);
+
+        let height = (window_height - MARGIN) / self.charts.len().max(1) as f64;
+        for (i, chart) in self.charts.iter().enumerate() {
+            let area = [left + MARGIN, MARGIN + i as f64 * height, PANEL_WIDTH - 2.0 * MARGIN, height - MARGIN];
+            draw_chart(chart, area, glyphs, c, g);
+        }
+    }
+}
+
+/// Draws `chart` in the rectangle `area`, its title and latest values above
+/// the plot.
+fn draw_chart(chart : &Chart, area : [f64; 4], glyphs : &mut Glyphs, c : Context, g : &mut G2d) {
+    let [x, y, width, height] = area;
+    let mut title_x = x;
+    let mut write = |text : &str, color : [f32; 4], glyphs : &mut Glyphs| {
+        Text::new_color(color, FONT_SIZE).draw(text, glyphs, &c.draw_state, c.transform.trans(title_x, y + TITLE_HEIGHT - 4.0), g).unwrap();
+        title_x += glyphs.width(FONT_SIZE, text).unwrap_or(0.0);
+    };
+    write(chart.title, TEXT_COLOR, glyphs);
+    for line in chart.lines.iter() {
+        let value = line.latest().map_or("-".to_string(), label);
+        write(&format!("  {} {}", line.name, value), line.color, glyphs);
+    }
+
+    let plot = [x, y + TITLE_HEIGHT, width, height - TITLE_HEIGHT];
+    rectangle(PLOT_COLOR, plot, c.transform, g);
+    let [min, max] = match chart.range() {
+        Some(range) => range,
+        None => return
+    };
+    let bounds = Text::new_color(AXIS_COLOR, FONT_SIZE - 2);
+    bounds.draw(&label(max), glyphs, &c.draw_state, c.transform.trans(plot[0] + 2.0, plot[1] + FONT_SIZE as f64), g).unwrap();
+    bounds.draw(&label(min), glyphs, &c.draw_state, c.transform.trans(plot[0] + 2.0, plot[1] + plot[3] - 2.0), g).unwrap();
+
+    let point = |index : usize, value : f64| [
+        plot[0] + index as f64 * plot[2] / (CHART_HISTORY - 1) as f64,
+        plot[1] + plot[3] * (1.0 - (value - min) / (max - min))
+    ];
+    for line in chart.lines.iter() {
+        for (index, pair) in line.values.windows(2).enumerate() {
+            if pair[0].is_finite() && pair[1].is_finite() {
+                let [x1, y1] = point(index, pair[0]);
+                let [x2, y2] = point(index + 1, pair[1]);
+                line_from_to(line.color, 1.0, [x1, y1], [x2, y2], c.transform, g);
+            }
+        }
+    }
+}
@@ -1,5 +1,9 @@
+use crate::display::palette::{element_color, human_color};
 use crate::simulation::world::{Element, World};
 use crate::simulation::actors::humans::Human;
+use crate::simulation::render::RenderFrame;
+use crate::simulation::replay::HumanFrame;
+use crate::types::Grid;
 
 use piston_window::*;
 pub trait Drawable {
@@ -8,77 +12,53 @@ pub trait Drawable {
 
 impl Drawable for Element {
     fn draw(&self, x : f64, y : f64, cell_size : f64, c: Context, g : &mut G2d) {
-        match self {
-            Element::Tree(val) =>
-                    rectangle(
-                        [0.2 , 0.8 * (1.0 - 0.2* val) as f32, 
-                                0.3 * (2.0 - val) as f32, 
-                                1.0],
-                        [x as f64 * cell_size, y as f64 *cell_size, cell_size, cell_size], // rectangle
-                        c.transform, g),
-            Element::Water(val) => 
-                    rectangle(
-                        [0.3, 0.3, 0.7 * (1.0 - val * 0.2) as f32, 1.0],
-                        [x as f64 * cell_size, y as f64 *cell_size, cell_size, cell_size], // rectangle
-                        c.transform, g),
-            Element::Grass(val) => 
-                    rectangle(
-                        [0.0, 0.7 * (1.0 - val * 0.8) as f32, 0.0, 1.0],
-                        [x as f64 * cell_size, y as f64 *cell_size, cell_size, cell_size], // rectangle
-                        c.transform, g),
-            Element::House(_) => 
-                    rectangle(
-                        [0.3, 0.1, 0.2, 1.0],
-                        [x as f64 * cell_size, y as f64 *cell_size, cell_size, cell_size], // rectangle
-                        c.transform, g),
-            Element::None => 
-                    rectangle(
-                        [0.0, 0.0, 0.0, 0.0],
-                        [x as f64 * cell_size, y as f64 *cell_size, cell_size, cell_size], // rectangle
-                        c.transform, g)
-        }
+        rectangle(element_color(self), [x * cell_size, y * cell_size, cell_size, cell_size], c.transform, g);
     }
 }
 
+fn draw_human(color : [f32; 4], x : f64, y : f64, cell_size : f64, c: Context, g : &mut G2d) {
+    ellipse(
+        color,
+        [x * cell_size, 
+        y * cell_size, 
+        cell_size, 
+        cell_size], // rectangle
+        c.transform, g);
+}
+
 impl  Drawable for Human {
     fn draw(&self, x : f64, y : f64, cell_size : f64, c: Context, g : &mut G2d) {
-        let color = if !self.alive {[0.0, 0.0, 0.0, 0.3]} 
-        else {
-            let mut out_color = [0.0, 0.0, 0.0, 1.0];
-            out_color[0] = self.thirst.value as f32 * 0.01;
-            out_color[2] = self.hunger.value as f32 * 0.01;
-            out_color
-        };
-        ellipse(
-            color,
-            [x * cell_size, 
-            y * cell_size, 
-            cell_size, 
-            cell_size], // rectangle
-            c.transform, g);
+        draw_human(human_color(self.alive, self.thirst.value, self.hunger.value), x, y, cell_size, c, g);
+    }
+}
+
+impl Drawable for HumanFrame {
+    fn draw(&self, x : f64, y : f64, cell_size : f64, c: Context, g : &mut G2d) {
+        draw_human(human_color(self.alive, self.thirst, self.hunger), x, y, cell_size, c, g);
+    }
+}
+
+fn draw_cells(cells : &Grid<Element>, cell_size : f64, c : Context, g : &mut G2d) {
+    for (position, element) in cells.iter() {
+        element.draw(position.x as f64, position.y as f64, cell_size, c, g);
     }
 }
 
 impl Drawable for World {
 
     fn draw(&self, _ : f64 , _ : f64, cell_size : f64, c: Context, g : &mut G2d) {
-        
-        let width = self.environment.read().unwrap().cells.len();
-        if width == 0 {
-            return;
-        }
-        let height = self.environment.read().unwrap().cells[0].len();
-        if height == 0 {
-            return;
+        draw_cells(&self.environment.read().unwrap().cells, cell_size, c, g);
+        for human in self.humans.iter() {
+            human.draw(human.position.x as f64, human.position.y as f64, cell_size, c, g);
         }
+    }
+}
 
-        for x in 0..width {
-            for y in 0..height {
-                self.environment.read().unwrap().cells[x][y].draw(x as f64, y as f64, cell_size, c, g);
-            }
-        }
+impl Drawable for RenderFrame {
+    fn draw(&self, _ : f64, _ : f64, cell_size : f64, c : Context, g : &mut G2d) {
+        draw_cells(&self.cells, cell_size, c, g);
         for human in self.humans.iter() {
             human.draw(human.position.x as f64, human.position.y as f64, cell_size, c, g);
         }
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,223 @@
+use crate::scenario::{behaviour_name, HumanConfig, LearnedBehaviours, Scenario};
+use crate::simulation::map;
+use crate::simulation::render::FramePublisher;
+use crate::simulation::scheduler::Scheduler;
+use crate::simulation::world::{Element, World};
+use crate::types::Position;
+
+use piston_window::*;
+
+use serde::Serialize;
+
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+/// Elements the brush paints, selected with the keys 1 to 5.
+pub const BRUSHES : [Element; 5] = [Element::None, Element::Grass(1.0), Element::Tree(1.0), Element::Water(1.0), Element::House(1.0)];
+const BRUSH_NAMES : [&str; 5] = ["empty", "grass", "tree", "water", "house"];
+const MAX_RADIUS : usize = 10;
+
+/// Living humans of an edited world, as the `[[humans]]` of a scenario.
+#[derive(Serialize)]
+struct SavedHumans {
+    humans : Vec<HumanConfig>
+}
+
+/// Edits the world of a paused scheduler from the window.
+///
+/// E enters and leaves the editor, the simulation is paused meanwhile. The
+/// left button paints the chosen element, the right one erases. 1 to 5 pick
+/// the element, [ and ] change the brush size. N switches to placing humans :
+/// the left button adds one, the right one removes the human under the
+/// cursor, tab picks the behaviour of the new humans. S saves the map, and
+/// the living humans next to it.
+pub struct Editor {
+    pub active : bool,
+    pub brush : usize,
+    pub radius : usize,
+    pub placing_humans : bool,
+    /// Outcome of the last save or of a refused exit.
+    pub message : Option<String>,
+    scenario : Scenario,
+    learned : LearnedBehaviours,
+    // Behaviours humans can be placed with.
+    names : Vec<String>,
+    behaviour : usize,
+    // Behaviour of each human of the world, in the same order.
+    human_behaviours : Vec<String>,
+    world : Arc<Mutex<World>>,
+    // Shows the edits while the simulation is paused.
+    publisher : FramePublisher,
+    map_file : PathBuf,
+    was_paused : bool,
+    held : Option<MouseButton>,
+    cursor : [f64; 2],
+    last_cell : Option<Position>
+}
+
+impl Editor {
+    pub fn new(scenario : &Scenario, learned : &LearnedBehaviours, scheduler : &Scheduler, publisher : FramePublisher, map_file : PathBuf) -> Editor {
+        let names = scenario.behaviour_names(learned).into_iter()
+            .filter(|name| scenario.behaviour(name, learned).is_ok())
+            .collect();
+        Editor {
+            active : false,
+            brush : 2,
+            radius : 0,
+            placing_humans : false,
+            message : None,
+            scenario : scenario.clone(),
+            learned : learned.clone(),
+            names,
+            behaviour : 0,
+            // The world was populated from the scenario.
+            human_behaviours : scenario.humans.iter().map(|human| human.behaviour.clone()).collect(),
+            world : scheduler.world(),
+            publisher,
+            map_file,
+            was_paused : false,
+            held : None,
+            cursor : [0.0; 2],
+            last_cell : None
+        }
+    }
+
+    /// Enters or leaves the editor. Leaving is refused while the world has no
+    /// water or no tree, the humans need both to find their way.
+    pub fn toggle(&mut self, scheduler : &Scheduler) {
+        if !self.active {
+            self.was_paused = scheduler.status().paused;
+            scheduler.pause();
+            self.active = true;
+            self.message = None;
+            return;
+        }
+        let environment = self.world.lock().unwrap().environment.clone();
+        let environment = environment.read().unwrap();
+        if environment.lakes.is_empty() || environment.forests.is_empty() {
+            self.message = Some("the world needs water and trees".to_string());
+            return;
+        }
+        self.active = false;
+        self.held = None;
+        self.message = None;
+        if !self.was_paused {
+            scheduler.resume();
+        }
+    }
+
+    /// Applies the editor key `key`, returns whether it was one.
+    pub fn key(&mut self, key : Key) -> bool {
+        if !self.active {
+            return false;
+        }
+        match key {
+            Key::D1 | Key::D2 | Key::D3 | Key::D4 | Key::D5 => {
+                self.brush = key as usize - Key::D1 as usize;
+                self.placing_humans = false;
+            },
+            Key::LeftBracket => self.radius = self.radius.saturating_sub(1),
+            Key::RightBracket => self.radius = (self.radius + 1).min(MAX_RADIUS),
+            Key::N => self.placing_humans = !self.placing_humans,
+            Key::Tab if !self.names.is_empty() => self.behaviour = (self.behaviour + 1) % self.names.len(),
+            Key::S => self.save(),
+            _ => return false
+        }
+        true
+    }
+
+    /// File the humans are saved to, next to the map file.
+    fn humans_file(&self) -> PathBuf {
+        self.map_file.with_extension("humans.toml")
+    }
+
+    fn save(&mut self) {
+        let world = self.world.lock().unwrap();
+        let humans_file = self.humans_file();
+        let saved = map::save(&self.map_file, &world.environment.read().unwrap().cells)
+            .and_then(|_| {
+                let humans = world.humans.iter().zip(self.human_behaviours.iter())
+                .filter(|(human, _)| human.alive)
+                .map(|(human, behaviour)| HumanConfig { position : human.position, behaviour : behaviour_name(behaviour).to_string() })
+                .collect();
+                let text = toml::to_string(&SavedHumans { humans }).map_err(io::Error::other)?;
+                fs::write(&humans_file, text)
+            });
+        self.message = Some(match saved {
+            Ok(()) => format!("map saved to {} and {}", self.map_file.display(), humans_file.display()),
+            Err(error) => format!("could not save {} : {}", self.map_file.display(), error)
+        });
+    }
+
+    /// Paints or places humans with the mouse, `to_cell` gives the cell under
+    /// a window position.
+    pub fn event<F : Fn([f64; 2]) -> Position>(&mut self, e : &Event, to_cell : F) {
+        if !self.active {
+            return;
+        }
+        if let Some(cursor) = e.mouse_cursor_args() {
+            self.cursor = cursor;
+        }
+        if let Some(Button::Mouse(button)) = e.press_args() {
+            self.held = Some(button);
+            self.last_cell = None;
+        }
+        if let Some(Button::Mouse(button)) = e.release_args() {
+            if self.held == Some(button) {
+                self.held = None;
+            }
+        }
+
+        let (button, cell) = match self.held {
+            Some(button) => (button, to_cell(self.cursor)),
+            None => return
+        };
+        // Each cell is edited once per stroke, and humans once per click.
+        if self.last_cell == Some(cell) || (self.placing_humans && self.last_cell.is_some()) {
+            return;
+        }
+        self.last_cell = Some(cell);
+
+        let mut world = self.world.lock().unwrap();
+        if !world.environment.read().unwrap().cells.contains(cell) {
+            return;
+        }
+        match (self.placing_humans, button) {
+            (false, MouseButton::Left) => world.environment.write().unwrap().paint(cell, self.radius, BRUSHES[self.brush]),
+            (false, MouseButton::Right) => world.environment.write().unwrap().paint(cell, self.radius, Element::None),
+            (true, MouseButton::Left) => {
+                if let Some(name) = self.names.get(self.behaviour) {
+                    let behaviour = self.scenario.behaviour(name, &self.learned).unwrap();
+                    let human = self.scenario.new_human(cell, behaviour, &world);
+                    world.add_human(human);
+                    self.human_behaviours.push(name.clone());
+                }
+            },
+            (true, MouseButton::Right) => {
+                let here = |alive : bool| world.humans.iter().position(|human| human.alive == alive && human.position == cell);
+                if let Some(index) = here(true).or_else(|| here(false)) {
+                    world.remove_human(index);
+                    self.human_behaviours.remove(index);
+                }
+            },
+            _ => return
+        }
+        self.publisher.publish(&world);
+    }
+
+    /// State of the editor, for the HUD.
+    pub fn description(&self) -> Vec<String> {
+        if !self.active {
+            return Vec::new();
+        }
+        let mut lines = vec![if self.placing_humans {
+            format!("edit - humans : {}", self.names.get(self.behaviour).map_or("no behaviour", String::as_str))
+        } else {
+            format!("edit - {} brush, size {}", BRUSH_NAMES[self.brush], 2 * self.radius + 1)
+        }];
+        lines.extend(self.message.clone());
+        lines
+    }
+}
@@ -0,0 +1,85 @@
+use crate::display::raster;
+use crate::simulation::replay::HumanFrame;
+use crate::simulation::world::Element;
+use crate::types::Grid;
+
+use image::codecs::gif::{GifEncoder, Repeat};
+use image::{Delay, Frame};
+
+use std::fs::{self, File};
+use std::io::{self, BufWriter};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Quantization speed of the GIF encoder, from 1 (best) to 30 (fastest).
+const GIF_SPEED : i32 = 10;
+
+fn image_error(error : image::ImageError) -> io::Error {
+    io::Error::other(error)
+}
+
+enum Target {
+    /// Only the state of the last frame is kept, rendered and written on
+    /// `finish`.
+    Png(PathBuf, Option<(Grid<Element>, Vec<HumanFrame>)>),
+    /// One numbered PNG per frame in the directory.
+    Sequence(PathBuf),
+    Gif(Box<GifEncoder<BufWriter<File>>>)
+}
+
+/// Writes rendered frames of a world to images : a single PNG if the path
+/// ends in `.png`, an animated GIF if it ends in `.gif`, and a directory of
+/// numbered PNGs otherwise.
+pub struct FrameExporter {
+    target : Target,
+    cell_size : u32,
+    /// Time each frame of a GIF is shown.
+    delay : Duration,
+    frames : usize
+}
+
+impl FrameExporter {
+    pub fn create<P : AsRef<Path>>(path : P, cell_size : u32, delay : Duration) -> io::Result<FrameExporter> {
+        let path = path.as_ref();
+        let target = match path.extension().and_then(|extension| extension.to_str()) {
+            Some("png") => Target::Png(path.to_path_buf(), None),
+            Some("gif") => {
+                let mut encoder = GifEncoder::new_with_speed(BufWriter::new(File::create(path)?), GIF_SPEED);
+                encoder.set_repeat(Repeat::Infinite).map_err(image_error)?;
+                Target::Gif(Box::new(encoder))
+            },
+            _ => {
+                fs::create_dir_all(path)?;
+                Target::Sequence(path.to_path_buf())
+            }
+        };
+        Ok(FrameExporter { target, cell_size, delay, frames : 0 })
+    }
+
+    /// Adds the frame of `cells` and `humans`, rendered and written right
+    /// away unless the output is a single PNG.
+    pub fn add(&mut self, cells : &Grid<Element>, humans : &[HumanFrame]) -> io::Result<()> {
+        match &mut self.target {
+            Target::Png(_, Some((last_cells, last_humans))) => {
+                last_cells.clone_from(cells);
+                last_humans.clear();
+                last_humans.extend_from_slice(humans);
+            },
+            Target::Png(_, last) => *last = Some((cells.clone(), humans.to_vec())),
+            Target::Sequence(directory) => raster::render(cells, humans, self.cell_size)
+                .save(directory.join(format!("frame_{:05}.png", self.frames))).map_err(image_error)?,
+            Target::Gif(encoder) => encoder.encode_frame(Frame::from_parts(raster::render(cells, humans, self.cell_size), 0, 0, Delay::from_saturating_duration(self.delay)))
+                .map_err(image_error)?
+        }
+        self.frames += 1;
+        Ok(())
+    }
+
+    /// Writes what is left and returns the number of frames added.
+    pub fn finish(self) -> io::Result<usize> {
+        if let Target::Png(path, Some((cells, humans))) = &self.target {
+            raster::render(cells, humans, self.cell_size).save(path).map_err(image_error)?;
+        }
+        Ok(self.frames)
+    }
+}
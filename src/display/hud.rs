@@ -0,0 +1,114 @@
+use crate::simulation::actors::behaviour::ACTION_NAMES;
+use crate::simulation::render::RenderFrame;
+use crate::simulation::scheduler::SchedulerStatus;
+
+use piston_window::*;
+
+use std::collections::VecDeque;
+use std::io;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+pub const FONT_SIZE : u32 = 12;
+const LINE_HEIGHT : f64 = 15.0;
+const PADDING : f64 = 6.0;
+pub const TEXT_COLOR : [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+pub const BOX_COLOR : [f32; 4] = [0.0, 0.0, 0.0, 0.6];
+/// Frames older than this are left out of the tick rate.
+const RATE_WINDOW : Duration = Duration::from_secs(1);
+
+/// Font of the overlays, looked up in the `assets` folder.
+pub fn load_font(window : &mut PistonWindow) -> io::Result<Glyphs> {
+    let assets : PathBuf = find_folder::Search::ParentsThenKids(3, 3).for_folder("assets")
+        .map_err(|error| io::Error::new(io::ErrorKind::NotFound, error.to_string()))?;
+    window.load_font(assets.join("fonts").join("DejaVuSansMono.ttf"))
+}
+
+/// Draws `lines` in a translucent box whose top left corner is at `position`
+/// in window pixels, a negative coordinate is counted from the right or the
+/// bottom of the window.
+pub fn text_box(lines : &[String], position : [f64; 2], glyphs : &mut Glyphs, c : Context, g : &mut G2d) {
+    let width = lines.iter()
+        .map(|line| glyphs.width(FONT_SIZE, line).unwrap_or(0.0))
+        .fold(0.0, f64::max) + 2.0 * PADDING;
+    let height = lines.len() as f64 * LINE_HEIGHT + 2.0 * PADDING;
+    let [window_width, window_height] = c.get_view_size();
+    let x = if position[0] < 0.0 { window_width + position[0] - width } else { position[0] };
+    let y = if position[1] < 0.0 { window_height + position[1] - height } else { position[1] };
+
+    rectangle(BOX_COLOR, [x, y, width, height], c.transform, g);
+    let text = Text::new_color(TEXT_COLOR, FONT_SIZE);
+    for (i, line) in lines.iter().enumerate() {
+        let baseline = y + PADDING + (i + 1) as f64 * LINE_HEIGHT - 3.0;
+        text.draw(line, glyphs, &c.draw_state, c.transform.trans(x + PADDING, baseline), g).unwrap();
+    }
+}
+
+/// Ticks per second, measured over the frames shown during the last second.
+#[derive(Default)]
+pub struct TickRate {
+    samples : VecDeque<(Instant, u64)>
+}
+
+impl TickRate {
+    pub fn sample(&mut self, tick : u64) {
+        let now = Instant::now();
+        self.samples.push_back((now, tick));
+        while self.samples.front().is_some_and(|(time, _)| now - *time > RATE_WINDOW) {
+            self.samples.pop_front();
+        }
+    }
+
+    pub fn rate(&self) -> f64 {
+        match (self.samples.front(), self.samples.back()) {
+            (Some((start, first)), Some((end, last))) if end > start =>
+                (last - first) as f64 / (*end - *start).as_secs_f64(),
+            _ => 0.0
+        }
+    }
+}
+
+/// Tick, population and tick rate.
+pub fn status_lines(frame : &RenderFrame, status : &SchedulerStatus, rate : f64) -> Vec<String> {
+    vec![
+        format!("tick {}{}", frame.tick, if status.paused { " (paused)" } else { "" }),
+        format!("alive {}/{}", frame.alive(), frame.humans.len()),
+        format!("{:.1} ticks/s, step {} ms", rate, status.time_step.as_millis())
+    ]
+}
+
+/// Everything known about the human `index`.
+pub fn inspector_lines(frame : &RenderFrame, index : usize) -> Vec<String> {
+    let human = &frame.humans[index];
+    let mut lines = vec![
+        format!("human {} at ({}, {}){}", index, human.position.x, human.position.y, if human.alive { "" } else { ", dead" }),
+        format!("age {}", human.age),
+        format!("hunger {}  thirst {}", human.hunger, human.thirst),
+        format!("energy {}  money {}", human.energy, human.money),
+        format!("action {}", human.action.map_or("none", |action| ACTION_NAMES[action as usize]))
+    ];
+    if let Some(inspection) = frame.inspections[index] {
+        lines.push(format!("state {}", inspection.state));
+        let best = (0..inspection.q_values.len())
+            .max_by(|a, b| inspection.q_values[*a].total_cmp(&inspection.q_values[*b]))
+            .unwrap();
+        for (action, value) in inspection.q_values.iter().enumerate() {
+            lines.push(format!("{} {:>5} {:9.3}", if action == best { ">" } else { " " }, ACTION_NAMES[action], value));
+        }
+    }
+    lines
+}
+
+/// Index of the human on the cell `(x, y)`, the living ones first.
+pub fn human_at(frame : &RenderFrame, x : i32, y : i32) -> Option<usize> {
+    let here = |alive : bool| frame.humans.iter()
+        .position(|human| human.alive == alive && human.position.x == x && human.position.y == y);
+    here(true).or_else(|| here(false))
+}
+
+/// Outlines the cell of the human `index`.
+pub fn highlight(frame : &RenderFrame, index : usize, cell_size : f64, c : Context, g : &mut G2d) {
+    let position = frame.humans[index].position;
+    Rectangle::new_border([1.0, 1.0, 0.0, 1.0], 1.0)
+        .draw([position.x as f64 * cell_size, position.y as f64 * cell_size, cell_size, cell_size], &c.draw_state, c.transform, g);
+}
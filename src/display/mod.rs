@@ -1 +1,22 @@
-pub mod draw;
\ No newline at end of file
+#[cfg(feature = "graphics")]
+pub mod camera;
+#[cfg(feature = "graphics")]
+pub mod charts;
+#[cfg(feature = "graphics")]
+pub mod draw;
+#[cfg(feature = "graphics")]
+pub mod editor;
+pub mod export;
+#[cfg(feature = "graphics")]
+pub mod hud;
+#[cfg(feature = "graphics")]
+pub mod overlay;
+pub mod palette;
+pub mod raster;
+#[cfg(feature = "graphics")]
+pub mod replay;
+#[cfg(feature = "graphics")]
+pub mod sprites;
+pub mod terminal;
+#[cfg(feature = "graphics")]
+pub mod trails;
\ No newline at end of file
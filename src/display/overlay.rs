@@ -0,0 +1,162 @@
+use crate::scenario::LearnedBehaviours;
+use crate::simulation::actors::behaviour::{
+    PolicyMap, QLBehaviour, DRINK, EAT, MOVE_DOWN, MOVE_LEFT, MOVE_RIGHT, MOVE_UP, NB_NEED_LEVELS
+};
+use crate::simulation::world::Environment;
+
+use piston_window::*;
+
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
+/// Time after which a shown map is computed again, the policy and the cells
+/// change while the world runs.
+const REFRESH : Duration = Duration::from_secs(1);
+const NEED_LEVELS : [&str; NB_NEED_LEVELS] = ["> 80", "> 50", "> 20", "<= 20"];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OverlayMode {
+    Off,
+    /// Best value of each cell as a colour, with the greedy action on top.
+    Policy,
+    /// Number of times the state of each cell was learnt from.
+    Visits
+}
+
+impl OverlayMode {
+    pub fn next(self) -> OverlayMode {
+        match self {
+            OverlayMode::Off => OverlayMode::Policy,
+            OverlayMode::Policy => OverlayMode::Visits,
+            OverlayMode::Visits => OverlayMode::Off
+        }
+    }
+}
+
+/// Overlay showing what a Q-learning behaviour would do on every cell.
+///
+/// O cycles through the modes, T and H through the thirst and hunger levels
+/// the map is computed for, B through the Q-learning behaviours.
+pub struct PolicyOverlay {
+    pub mode : OverlayMode,
+    pub thirst_level : usize,
+    pub hunger_level : usize,
+    behaviours : Vec<(String, Arc<RwLock<QLBehaviour>>)>,
+    behaviour : usize,
+    environment : Arc<RwLock<Environment>>,
+    map : Option<PolicyMap>,
+    // When `map` was computed, `None` when it has to be computed again.
+    computed_at : Option<Instant>
+}
+
+impl PolicyOverlay {
+    pub fn new(learned : &LearnedBehaviours, environment : Arc<RwLock<Environment>>) -> PolicyOverlay {
+        PolicyOverlay {
+            mode : OverlayMode::Off,
+            thirst_level : 0,
+            hunger_level : 0,
+            behaviours : learned.iter().map(|(name, behaviour)| (name.clone(), behaviour.clone())).collect(),
+            behaviour : 0,
+            environment,
+            map : None,
+            computed_at : None
+        }
+    }
+
+    /// Applies the overlay key `key`, returns whether it was one.
+    pub fn key(&mut self, key : Key) -> bool {
+        match key {
+            Key::O => self.mode = self.mode.next(),
+            Key::T => self.thirst_level = (self.thirst_level + 1) % NB_NEED_LEVELS,
+            Key::H => self.hunger_level = (self.hunger_level + 1) % NB_NEED_LEVELS,
+            Key::B if !self.behaviours.is_empty() => self.behaviour = (self.behaviour + 1) % self.behaviours.len(),
+            _ => return false
+        }
+        self.computed_at = None;
+        true
+    }
+
+    /// Computes the map again if it is shown and out of date.
+    pub fn update(&mut self) {
+        if self.mode == OverlayMode::Off || self.behaviours.is_empty()
+            || self.computed_at.is_some_and(|time| time.elapsed() < REFRESH) {
+            return;
+        }
+        let environment = self.environment.read().unwrap().clone();
+        let behaviour = self.behaviours[self.behaviour].1.read().unwrap();
+        self.map = Some(behaviour.policy_map(&environment, self.thirst_level, self.hunger_level));
+        self.computed_at = Some(Instant::now());
+    }
+
+    /// What is shown, for the HUD.
+    pub fn description(&self) -> Option<String> {
+        if self.mode == OverlayMode::Off {
+            return None;
+        }
+        Some(match self.behaviours.get(self.behaviour) {
+            Some((name, _)) => format!("{} of {} - thirst {} - hunger {}",
+                if self.mode == OverlayMode::Policy { "policy" } else { "visits" },
+                name, NEED_LEVELS[self.thirst_level], NEED_LEVELS[self.hunger_level]),
+            None => "no Q-learning behaviour".to_string()
+        })
+    }
+
+    pub fn draw(&self, cell_size : f64, c : Context, g : &mut G2d) {
+        match (self.mode, &self.map) {
+            (OverlayMode::Policy, Some(map)) => draw_policy(map, cell_size, c, g),
+            (OverlayMode::Visits, Some(map)) => draw_visits(map, cell_size, c, g),
+            _ => ()
+        }
+    }
+}
+
+/// From blue for the lowest values to red for the highest.
+fn heat_color(ratio : f64) -> [f32; 4] {
+    let ratio = ratio.clamp(0.0, 1.0) as f32;
+    [ratio, 0.2, 1.0 - ratio, 0.55]
+}
+
+fn draw_policy(map : &PolicyMap, cell_size : f64, c : Context, g : &mut G2d) {
+    let values = map.values.values().copied().filter(|value| value.is_finite());
+    let (min, max) = values.fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), value| (min.min(value), max.max(value)));
+    let arrow = Line::new([1.0, 1.0, 1.0, 0.9], cell_size * 0.05);
+    for (position, action) in map.actions.iter() {
+        let (left, top) = (position.x as f64 * cell_size, position.y as f64 * cell_size);
+        let ratio = if max > min { (map.values[position] - min) / (max - min) } else { 0.5 };
+        rectangle(heat_color(ratio), [left, top, cell_size, cell_size], c.transform, g);
+
+        let (cx, cy) = (left + cell_size / 2.0, top + cell_size / 2.0);
+        let length = cell_size * 0.35;
+        let direction = match *action {
+            MOVE_RIGHT => Some((1.0, 0.0)),
+            MOVE_LEFT => Some((-1.0, 0.0)),
+            MOVE_DOWN => Some((0.0, 1.0)),
+            MOVE_UP => Some((0.0, -1.0)),
+            _ => None
+        };
+        match direction {
+            Some((dx, dy)) => arrow.draw_arrow([cx - dx * length, cy - dy * length, cx + dx * length, cy + dy * length],
+                cell_size * 0.2, &c.draw_state, c.transform, g),
+            // Actions in place are dots : blue to drink, green to eat, grey to wait.
+            None => {
+                let color = match *action {
+                    DRINK => [0.2, 0.4, 1.0, 1.0],
+                    EAT => [0.1, 0.8, 0.1, 1.0],
+                    _ => [0.7, 0.7, 0.7, 1.0]
+                };
+                ellipse(color, [cx - length / 2.0, cy - length / 2.0, length, length], c.transform, g);
+            }
+        }
+    }
+}
+
+fn draw_visits(map : &PolicyMap, cell_size : f64, c : Context, g : &mut G2d) {
+    // Log scale, a few cells are visited far more than the others.
+    let max = map.visits.values().copied().max().unwrap_or(0);
+    let scale = (1.0 + max as f64).ln().max(f64::EPSILON);
+    for (position, visits) in map.visits.iter() {
+        let ratio = ((1.0 + *visits as f64).ln() / scale) as f32;
+        rectangle([1.0, 1.0, 0.3, 0.1 + 0.7 * ratio], [position.x as f64 * cell_size, position.y as f64 * cell_size, cell_size, cell_size], c.transform, g);
+    }
+}
+
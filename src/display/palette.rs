@@ -0,0 +1,38 @@
+use crate::simulation::world::Element;
+
+/// Colour under the cells, empty cells let it show.
+pub const BACKGROUND : [f32; 4] = [0.4, 0.8, 0.5, 1.0];
+
+pub fn element_color(element : &Element) -> [f32; 4] {
+    match *element {
+        Element::Tree(val) => [0.2, 0.8 * (1.0 - 0.2 * val) as f32, 0.3 * (2.0 - val) as f32, 1.0],
+        Element::Water(val) => [0.3, 0.3, 0.7 * (1.0 - val * 0.2) as f32, 1.0],
+        Element::Grass(val) => [0.0, 0.7 * (1.0 - val * 0.8) as f32, 0.0, 1.0],
+        Element::House(_) => [0.3, 0.1, 0.2, 1.0],
+        Element::None => [0.0, 0.0, 0.0, 0.0]
+    }
+}
+
+/// Red with thirst, blue with hunger, faded once dead.
+pub fn human_color(alive : bool, thirst : i32, hunger : i32) -> [f32; 4] {
+    if !alive {[0.0, 0.0, 0.0, 0.3]} 
+    else {
+        let mut out_color = [0.0, 0.0, 0.0, 1.0];
+        out_color[0] = thirst as f32 * 0.01;
+        out_color[2] = hunger as f32 * 0.01;
+        out_color
+    }
+}
+
+/// Colours told apart on the background, for the `index`-th of several series.
+pub fn series_color(index : usize) -> [f32; 4] {
+    const COLORS : [[f32; 4]; 6] = [
+        [0.9, 0.1, 0.1, 1.0],
+        [0.1, 0.2, 0.9, 1.0],
+        [1.0, 1.0, 1.0, 1.0],
+        [0.9, 0.5, 0.0, 1.0],
+        [0.6, 0.0, 0.7, 1.0],
+        [0.0, 0.0, 0.0, 1.0]
+    ];
+    COLORS[index % COLORS.len()]
+}
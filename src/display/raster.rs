@@ -0,0 +1,127 @@
+use crate::display::palette::{element_color, human_color, series_color, BACKGROUND};
+use crate::simulation::render::RenderFrame;
+use crate::simulation::replay::HumanFrame;
+use crate::simulation::trajectory::Trajectory;
+use crate::simulation::world::Element;
+use crate::types::Grid;
+
+use image::{Rgba, RgbaImage};
+
+/// Software counterpart of `Drawable`, drawing into an image instead of a
+/// window, with the same colours.
+pub trait Rasterize {
+    fn rasterize(&self, x : f64, y : f64, cell_size : f64, image : &mut RgbaImage);
+}
+
+/// Blends `color` over the pixel `(x, y)`, if it is in the image.
+fn blend(image : &mut RgbaImage, x : i64, y : i64, color : [f32; 4]) {
+    if x < 0 || y < 0 || x >= image.width() as i64 || y >= image.height() as i64 {
+        return;
+    }
+    let pixel = image.get_pixel_mut(x as u32, y as u32);
+    let alpha = color[3].clamp(0.0, 1.0);
+    for channel in 0..3 {
+        let over = color[channel].clamp(0.0, 1.0) * 255.0;
+        pixel[channel] = (over * alpha + pixel[channel] as f32 * (1.0 - alpha)).round() as u8;
+    }
+    pixel[3] = 255;
+}
+
+/// Fills the rectangle `[x, y, width, height]` in pixels.
+fn fill_rectangle(image : &mut RgbaImage, rectangle : [f64; 4], color : [f32; 4]) {
+    let [x, y, width, height] = rectangle;
+    for py in y.round() as i64..(y + height).round() as i64 {
+        for px in x.round() as i64..(x + width).round() as i64 {
+            blend(image, px, py, color);
+        }
+    }
+}
+
+/// Fills the ellipse inscribed in the rectangle `[x, y, width, height]`,
+/// testing the centre of each pixel.
+fn fill_ellipse(image : &mut RgbaImage, rectangle : [f64; 4], color : [f32; 4]) {
+    let [x, y, width, height] = rectangle;
+    let (cx, cy, rx, ry) = (x + width / 2.0, y + height / 2.0, width / 2.0, height / 2.0);
+    for py in y.floor() as i64..(y + height).ceil() as i64 {
+        for px in x.floor() as i64..(x + width).ceil() as i64 {
+            let (dx, dy) = ((px as f64 + 0.5 - cx) / rx, (py as f64 + 0.5 - cy) / ry);
+            if dx * dx + dy * dy <= 1.0 {
+                blend(image, px, py, color);
+            }
+        }
+    }
+}
+
+/// Draws a line a pixel wide from `from` to `to`, one blended pixel per step
+/// along its longest axis.
+fn draw_line(image : &mut RgbaImage, from : [f64; 2], to : [f64; 2], color : [f32; 4]) {
+    let steps = (to[0] - from[0]).abs().max((to[1] - from[1]).abs()).ceil().max(1.0) as i64;
+    // The first pixel is left to the previous segment, so that joints are not
+    // blended twice.
+    for step in 1..=steps {
+        let t = step as f64 / steps as f64;
+        let (x, y) = (from[0] + (to[0] - from[0]) * t, from[1] + (to[1] - from[1]) * t);
+        blend(image, x.floor() as i64, y.floor() as i64, color);
+    }
+}
+
+impl Rasterize for Element {
+    fn rasterize(&self, x : f64, y : f64, cell_size : f64, image : &mut RgbaImage) {
+        fill_rectangle(image, [x * cell_size, y * cell_size, cell_size, cell_size], element_color(self));
+    }
+}
+
+impl Rasterize for HumanFrame {
+    fn rasterize(&self, x : f64, y : f64, cell_size : f64, image : &mut RgbaImage) {
+        fill_ellipse(image, [x * cell_size, y * cell_size, cell_size, cell_size], human_color(self.alive, self.thirst, self.hunger));
+    }
+}
+
+impl Rasterize for RenderFrame {
+    fn rasterize(&self, _ : f64, _ : f64, cell_size : f64, image : &mut RgbaImage) {
+        rasterize_cells(&self.cells, &self.humans, cell_size, image);
+    }
+}
+
+/// Draws cells and the humans over them.
+pub fn rasterize_cells(cells : &Grid<Element>, humans : &[HumanFrame], cell_size : f64, image : &mut RgbaImage) {
+    for (position, element) in cells.iter() {
+        element.rasterize(position.x as f64, position.y as f64, cell_size, image);
+    }
+    for human in humans {
+        human.rasterize(human.position.x as f64, human.position.y as f64, cell_size, image);
+    }
+}
+
+/// Image of a world, `cell_size` pixels per cell, on the window background.
+pub fn render(cells : &Grid<Element>, humans : &[HumanFrame], cell_size : u32) -> RgbaImage {
+    let width = cells.width() as u32 * cell_size;
+    let height = cells.height() as u32 * cell_size;
+    let background = BACKGROUND.map(|channel| (channel * 255.0).round() as u8);
+    let mut image = RgbaImage::from_pixel(width, height, Rgba(background));
+    rasterize_cells(cells, humans, cell_size as f64, &mut image);
+    image
+}
+
+/// Image of `cells` with `trajectories` drawn over them as lines between the
+/// centres of the cells, a colour per behaviour and a dot at each start.
+/// Translucent lines make the paths taken often stand out.
+pub fn render_trajectories(cells : &Grid<Element>, trajectories : &[Trajectory], cell_size : u32) -> RgbaImage {
+    let mut image = render(cells, &[], cell_size);
+    let mut behaviours : Vec<&str> = trajectories.iter().map(|trajectory| trajectory.behaviour.as_str()).collect();
+    behaviours.dedup();
+    let size = cell_size as f64;
+    let centre = |point : [i32; 2]| [(point[0] as f64 + 0.5) * size, (point[1] as f64 + 0.5) * size];
+    for trajectory in trajectories {
+        let index = behaviours.iter().position(|name| *name == trajectory.behaviour).unwrap();
+        let [r, g, b, _] = series_color(index);
+        for pair in trajectory.points.windows(2) {
+            draw_line(&mut image, centre(pair[0]), centre(pair[1]), [r, g, b, 0.3]);
+        }
+        if let Some(start) = trajectory.points.first() {
+            let [x, y] = centre(*start);
+            fill_ellipse(&mut image, [x - size / 4.0, y - size / 4.0, size / 2.0, size / 2.0], [r, g, b, 1.0]);
+        }
+    }
+    image
+}
@@ -0,0 +1,133 @@
+use crate::display::sprites::SpriteSet;
+use crate::simulation::replay::ReplayPlayer;
+
+use piston_window::*;
+
+/// Height in pixels of the timeline under the world.
+pub const TIMELINE_HEIGHT : f64 = 12.0;
+const BASE_SPEED : f64 = 10.0;
+const MAX_SPEED : f64 = 1000.0;
+const MIN_SPEED : f64 = 0.5;
+
+/// Plays a replay in a Piston window.
+///
+/// Space pauses, left and right step one frame, up and down double or halve
+/// the speed, home and end jump to the ends. Clicking or dragging on the
+/// timeline scrubs through the recording.
+pub struct ReplayViewer {
+    pub player : ReplayPlayer,
+    pub cell_size : f64,
+    pub playing : bool,
+    /// Frames per second.
+    pub speed : f64,
+    // Time not yet spent on a frame.
+    elapsed : f64,
+    cursor : [f64; 2],
+    scrubbing : bool
+}
+
+impl ReplayViewer {
+    pub fn new(player : ReplayPlayer, cell_size : f64) -> ReplayViewer {
+        ReplayViewer { player, cell_size, playing : true, speed : BASE_SPEED, elapsed : 0.0, cursor : [0.0; 2], scrubbing : false }
+    }
+
+    /// Size in pixels of the world.
+    pub fn world_size(&self) -> [f64; 2] {
+        let cells = self.player.cells();
+        [cells.width() as f64 * self.cell_size, cells.height() as f64 * self.cell_size]
+    }
+
+    /// Window size fitting the world and the timeline.
+    pub fn window_size(&self) -> [f64; 2] {
+        let [width, height] = self.world_size();
+        [width, height + TIMELINE_HEIGHT]
+    }
+
+    fn on_timeline(&self) -> bool {
+        let [width, height] = self.world_size();
+        self.cursor[1] >= height && self.cursor[0] >= 0.0 && self.cursor[0] <= width
+    }
+
+    fn scrub(&mut self) {
+        let width = self.world_size()[0].max(1.0);
+        let last = self.player.nb_frames() - 1;
+        let frame = (self.cursor[0] / width * last as f64).round().max(0.0) as usize;
+        self.player.seek(frame);
+    }
+
+    pub fn event(&mut self, e : &Event) {
+        if let Some(args) = e.update_args() {
+            if self.playing {
+                self.elapsed += args.dt * self.speed;
+                let frames = self.elapsed.floor();
+                self.elapsed -= frames;
+                self.player.seek(self.player.frame() + frames as usize);
+                if self.player.frame() + 1 == self.player.nb_frames() {
+                    self.playing = false;
+                }
+            }
+        }
+
+        if let Some(cursor) = e.mouse_cursor_args() {
+            self.cursor = cursor;
+            if self.scrubbing {
+                self.scrub();
+            }
+        }
+
+        if let Some(button) = e.press_args() {
+            match button {
+                Button::Keyboard(Key::Space) => {
+                    if !self.playing && self.player.frame() + 1 == self.player.nb_frames() {
+                        self.player.seek(0);
+                    }
+                    self.playing = !self.playing;
+                },
+                Button::Keyboard(Key::Right) => {
+                    self.playing = false;
+                    self.player.seek(self.player.frame() + 1);
+                },
+                Button::Keyboard(Key::Left) => {
+                    self.playing = false;
+                    self.player.seek(self.player.frame().saturating_sub(1));
+                },
+                Button::Keyboard(Key::Up) => self.speed = (self.speed * 2.0).min(MAX_SPEED),
+                Button::Keyboard(Key::Down) => self.speed = (self.speed / 2.0).max(MIN_SPEED),
+                Button::Keyboard(Key::Home) => self.player.seek(0),
+                Button::Keyboard(Key::End) => self.player.seek(usize::MAX),
+                Button::Mouse(MouseButton::Left) if self.on_timeline() => {
+                    self.scrubbing = true;
+                    self.scrub();
+                },
+                _ => ()
+            }
+        }
+
+        if let Some(Button::Mouse(MouseButton::Left)) = e.release_args() {
+            self.scrubbing = false;
+        }
+    }
+
+    /// Window title describing the playback.
+    pub fn title(&self) -> String {
+        format!("Replay - tick {} ({}/{}) - x{}{}",
+            self.player.tick(), self.player.frame(), self.player.nb_frames() - 1,
+            self.speed, if self.playing { "" } else { " - paused" })
+    }
+
+    pub fn draw(&self, sprites : &SpriteSet, c : Context, g : &mut G2d) {
+        sprites.draw_world(self.player.cells(), self.player.humans(), self.cell_size, c, g);
+
+        let [width, height] = self.world_size();
+        let progress = self.player.frame() as f64 / (self.player.nb_frames() - 1).max(1) as f64;
+        rectangle([0.1, 0.1, 0.1, 1.0], [0.0, height, width, TIMELINE_HEIGHT], c.transform, g);
+        rectangle([0.9, 0.9, 0.9, 1.0], [0.0, height + 2.0, width * progress, TIMELINE_HEIGHT - 4.0], c.transform, g);
+        // Deaths are marked on the timeline.
+        for (frame, record) in self.player.replay().ticks.iter().enumerate() {
+            if !record.deaths.is_empty() {
+                let x = (frame + 1) as f64 / (self.player.nb_frames() - 1).max(1) as f64 * width;
+                rectangle([0.8, 0.1, 0.1, 1.0], [x - 0.5, height, 1.0, TIMELINE_HEIGHT], c.transform, g);
+            }
+        }
+    }
+}
@@ -0,0 +1,132 @@
+use crate::display::draw::Drawable;
+use crate::simulation::replay::HumanFrame;
+use crate::simulation::world::Element;
+use crate::types::Grid;
+
+use piston_window::*;
+use sprite::Sprite;
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::rc::Rc;
+
+/// Number of tile variants of an element, from full to nearly used up.
+pub const NB_VARIANTS : usize = 3;
+const INDICATORS : [&str; 3] = ["thirsty", "hungry", "resting"];
+
+/// File name prefix of the tiles of `element`, `None` for empty cells.
+fn tile_name(element : &Element) -> Option<(&'static str, f64)> {
+    match *element {
+        Element::None => None,
+        Element::Grass(amount) => Some(("grass", amount)),
+        Element::Tree(amount) => Some(("tree", amount)),
+        Element::Water(amount) => Some(("water", amount)),
+        Element::House(amount) => Some(("house", amount))
+    }
+}
+
+/// Tile variant of a resource holding `amount` out of 1.
+fn variant(amount : f64) -> usize {
+    if amount > 2.0 / 3.0 { 0 } else if amount > 1.0 / 3.0 { 1 } else { 2 }
+}
+
+/// Sprites of the cells and the humans, loaded from `assets/sprites`.
+///
+/// Tiles are named after their element and variant, `tree_0.png` for a full
+/// tree up to `tree_2.png` for a nearly eaten one. Humans use `human.png` and
+/// `human_dead.png`, with `thirsty.png`, `hungry.png` and `resting.png` drawn
+/// over them as indicators. Whatever is missing is drawn with the primitives
+/// of `Drawable`, a variant falls back to variant 0 first.
+#[derive(Default)]
+pub struct SpriteSet {
+    sprites : HashMap<String, Sprite<G2dTexture>>
+}
+
+impl SpriteSet {
+    /// Loads the sprites found in the `assets/sprites` folder, none if it
+    /// cannot be found.
+    pub fn load(window : &mut PistonWindow) -> SpriteSet {
+        match find_folder::Search::ParentsThenKids(3, 3).for_folder("assets") {
+            Ok(assets) => SpriteSet::load_from(window, &assets.join("sprites")),
+            Err(_) => SpriteSet::default()
+        }
+    }
+
+    pub fn load_from(window : &mut PistonWindow, folder : &Path) -> SpriteSet {
+        let mut names : Vec<String> = ["grass", "tree", "water", "house"].iter()
+            .flat_map(|tile| (0..NB_VARIANTS).map(move |variant| format!("{tile}_{variant}")))
+            .collect();
+        names.extend(["human", "human_dead"].iter().chain(INDICATORS.iter()).map(|name| name.to_string()));
+
+        // Nearest filtering keeps the pixel art sharp when zoomed.
+        let settings = TextureSettings::new().filter(Filter::Nearest);
+        let mut context = window.create_texture_context();
+        let mut sprites = HashMap::new();
+        for name in names {
+            if let Ok(texture) = Texture::from_path(&mut context, folder.join(format!("{name}.png")), Flip::None, &settings) {
+                let mut sprite = Sprite::from_texture(Rc::new(texture));
+                sprite.set_anchor(0.0, 0.0);
+                sprites.insert(name, sprite);
+            }
+        }
+        SpriteSet { sprites }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sprites.is_empty()
+    }
+
+    /// Draws `name` stretched over the square of side `size` at `(x, y)` in
+    /// pixels. Returns false if there is no such sprite.
+    fn draw_sprite(&self, name : &str, x : f64, y : f64, size : f64, c : Context, g : &mut G2d) -> bool {
+        match self.sprites.get(name) {
+            Some(sprite) => {
+                let (width, height) = sprite.get_texture().get_size();
+                sprite.draw(c.transform.trans(x, y).scale(size / width as f64, size / height as f64), g);
+                true
+            },
+            None => false
+        }
+    }
+
+    pub fn draw_element(&self, element : &Element, x : f64, y : f64, cell_size : f64, c : Context, g : &mut G2d) {
+        let (tile, amount) = match tile_name(element) {
+            Some(tile) => tile,
+            None => return element.draw(x, y, cell_size, c, g)
+        };
+        let (left, top) = (x * cell_size, y * cell_size);
+        if !self.draw_sprite(&format!("{tile}_{}", variant(amount)), left, top, cell_size, c, g)
+            && !self.draw_sprite(&format!("{tile}_0"), left, top, cell_size, c, g) {
+            element.draw(x, y, cell_size, c, g);
+        }
+    }
+
+    pub fn draw_human(&self, human : &HumanFrame, cell_size : f64, c : Context, g : &mut G2d) {
+        let (x, y) = (human.position.x as f64, human.position.y as f64);
+        let (left, top) = (x * cell_size, y * cell_size);
+        let body = if human.alive { "human" } else { "human_dead" };
+        if !self.draw_sprite(body, left, top, cell_size, c, g) {
+            return human.draw(x, y, cell_size, c, g);
+        }
+
+        // Indicators line up along the top of the cell, from the right.
+        let size = cell_size / 2.5;
+        let shown = [human.thirsty(), human.hungry(), human.resting()];
+        let mut right = left + cell_size;
+        for (name, _) in INDICATORS.iter().zip(shown).filter(|(_, shown)| *shown) {
+            if self.draw_sprite(name, right - size, top, size, c, g) {
+                right -= size;
+            }
+        }
+    }
+
+    /// Draws cells and the humans over them.
+    pub fn draw_world(&self, cells : &Grid<Element>, humans : &[HumanFrame], cell_size : f64, c : Context, g : &mut G2d) {
+        for (position, element) in cells.iter() {
+            self.draw_element(element, position.x as f64, position.y as f64, cell_size, c, g);
+        }
+        for human in humans {
+            self.draw_human(human, cell_size, c, g);
+        }
+    }
+}
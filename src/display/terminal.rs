@@ -0,0 +1,129 @@
+use crate::display::palette::{element_color, human_color, BACKGROUND};
+use crate::simulation::replay::HumanFrame;
+use crate::simulation::world::Element;
+use crate::types::{Grid, Position};
+
+use std::fmt::Write as _;
+use std::io::{self, Write};
+
+/// Moves the cursor to the top left corner, so that frames overwrite each other.
+const HOME : &str = "\x1b[H";
+const CLEAR_SCREEN : &str = "\x1b[2J";
+/// Clears from the cursor to the end of the screen.
+const CLEAR_BELOW : &str = "\x1b[J";
+const RESET : &str = "\x1b[0m";
+
+/// Character standing for `element` without colours.
+fn element_char(element : &Element) -> char {
+    match *element {
+        Element::None => ' ',
+        Element::Grass(_) => '.',
+        Element::Tree(amount) => if amount > 0.5 { 'T' } else { 't' },
+        Element::Water(amount) => if amount > 0.5 { '~' } else { '-' },
+        Element::House(_) => '#'
+    }
+}
+
+/// `color` over the background, as 8 bit channels.
+fn rgb(color : [f32; 4]) -> [u8; 3] {
+    let alpha = color[3].clamp(0.0, 1.0);
+    let mut channels = [0; 3];
+    for (channel, (over, under)) in channels.iter_mut().zip(color.iter().zip(BACKGROUND)) {
+        *channel = ((over.clamp(0.0, 1.0) * alpha + under * (1.0 - alpha)) * 255.0).round() as u8;
+    }
+    channels
+}
+
+/// Mean and minimum of a need over the living humans.
+fn need_summary<F : Fn(&HumanFrame) -> i32>(humans : &[HumanFrame], need : F) -> String {
+    let values : Vec<i32> = humans.iter().filter(|human| human.alive).map(need).collect();
+    match values.iter().min() {
+        Some(min) => format!("{:.0} (min {})", values.iter().sum::<i32>() as f64 / values.len() as f64, min),
+        None => "-".to_string()
+    }
+}
+
+/// Prints worlds to a terminal, two characters per cell so that cells are
+/// about square. With colours, cells are drawn as 24 bit ANSI backgrounds in
+/// the colours of the window, otherwise as characters : `.` grass, `T` tree,
+/// `~` water, `#` house, lower case or `-` once half used. Humans are `@`,
+/// dead ones `x`.
+pub struct TerminalRenderer {
+    pub colors : bool,
+    // Clears the screen before the first frame only, later frames are drawn
+    // over the previous one to avoid flickering.
+    started : bool
+}
+
+impl TerminalRenderer {
+    pub fn new(colors : bool) -> TerminalRenderer {
+        TerminalRenderer { colors, started : false }
+    }
+
+    /// Text of a frame : the cells one row per line, the humans over them,
+    /// then a status line.
+    pub fn frame(&self, tick : u64, cells : &Grid<Element>, humans : &[HumanFrame]) -> String {
+        // Living humans are drawn over the dead ones on the same cell.
+        let mut shown : Grid<Option<&HumanFrame>> = Grid::new(cells.width(), cells.height(), None);
+        for human in humans {
+            if let Some(cell) = shown.get_mut(human.position) {
+                if cell.is_none_or(|other| !other.alive) {
+                    *cell = Some(human);
+                }
+            }
+        }
+
+        let mut text = String::new();
+        for y in 0..cells.height() as i32 {
+            // Escape sequences are only written when the background changes.
+            let mut background = None;
+            for x in 0..cells.width() as i32 {
+                let position = Position::new(x, y);
+                let element = &cells[position];
+                let human = shown[position];
+                if self.colors {
+                    let [r, g, b] = rgb(element_color(element));
+                    if background != Some([r, g, b]) {
+                        write!(text, "\x1b[48;2;{r};{g};{b}m").unwrap();
+                        background = Some([r, g, b]);
+                    }
+                    match human {
+                        Some(human) => {
+                            let [r, g, b] = rgb(human_color(human.alive, human.thirst, human.hunger));
+                            let symbol = if human.alive { '@' } else { 'x' };
+                            write!(text, "\x1b[1;38;2;{r};{g};{b}m{symbol} \x1b[22m").unwrap();
+                        },
+                        None => text.push_str("  ")
+                    }
+                } else {
+                    match human {
+                        Some(human) => text.push_str(if human.alive { "@ " } else { "x " }),
+                        None => (0..2).for_each(|_| text.push(element_char(element)))
+                    }
+                }
+            }
+            if self.colors {
+                text.push_str(RESET);
+            }
+            text.push('\n');
+        }
+
+        let alive = humans.iter().filter(|human| human.alive).count();
+        writeln!(text, "tick {tick} | alive {alive}/{} | thirst {} | hunger {} | energy {}",
+            humans.len(),
+            need_summary(humans, |human| human.thirst),
+            need_summary(humans, |human| human.hunger),
+            need_summary(humans, |human| human.energy)).unwrap();
+        text
+    }
+
+    /// Writes a frame over the previous one.
+    pub fn draw<W : Write>(&mut self, writer : &mut W, tick : u64, cells : &Grid<Element>, humans : &[HumanFrame]) -> io::Result<()> {
+        if !self.started {
+            writer.write_all(CLEAR_SCREEN.as_bytes())?;
+            self.started = true;
+        }
+        write!(writer, "{HOME}{}{CLEAR_BELOW}", self.frame(tick, cells, humans))?;
+        writer.flush()
+    }
+}
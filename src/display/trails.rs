@@ -0,0 +1,61 @@
+use crate::simulation::trajectory::Trails;
+use crate::types::Position;
+
+use piston_window::*;
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+const TRAIL_COLOR : [f32; 4] = [1.0, 1.0, 1.0, 0.8];
+const TRAIL_WIDTH : f64 = 0.1;
+
+/// Fading lines through the last positions of each human, recorded by the
+/// simulation. P shows or hides them.
+pub struct TrailOverlay {
+    pub visible : bool,
+    trails : Arc<Mutex<Trails>>,
+    // Copy of the trails as of `tick`, drawn without holding the lock.
+    shown : Vec<VecDeque<Position>>,
+    tick : Option<u64>
+}
+
+impl TrailOverlay {
+    pub fn new(trails : Arc<Mutex<Trails>>) -> TrailOverlay {
+        TrailOverlay { visible : false, trails, shown : Vec::new(), tick : None }
+    }
+
+    /// Applies the trail key `key`, returns whether it was one.
+    pub fn key(&mut self, key : Key) -> bool {
+        if key != Key::P {
+            return false;
+        }
+        self.visible = !self.visible;
+        true
+    }
+
+    /// Copies the trails when the frame shown moved on to `tick`.
+    pub fn update(&mut self, tick : u64) {
+        if self.visible && self.tick != Some(tick) {
+            self.tick = Some(tick);
+            self.shown = self.trails.lock().unwrap().trails().to_vec();
+        }
+    }
+
+    /// Draws the trails in world coordinates, older segments more faded.
+    pub fn draw(&self, cell_size : f64, c : Context, g : &mut G2d) {
+        if !self.visible {
+            return;
+        }
+        let centre = |position : &Position| [(position.x as f64 + 0.5) * cell_size, (position.y as f64 + 0.5) * cell_size];
+        for trail in self.shown.iter() {
+            let segments = trail.len().saturating_sub(1);
+            for (i, (from, to)) in trail.iter().zip(trail.iter().skip(1)).enumerate() {
+                let mut color = TRAIL_COLOR;
+                color[3] *= (i + 1) as f32 / segments as f32;
+                let [x1, y1] = centre(from);
+                let [x2, y2] = centre(to);
+                line_from_to(color, TRAIL_WIDTH * cell_size, [x1, y1], [x2, y2], c.transform, g);
+            }
+        }
+    }
+}
@@ -0,0 +1,71 @@
+use crate::learning::qlearning::{Agent, State};
+
+use std::fmt;
+
+/// Survival metrics gathered over evaluation episodes.
+#[derive(Clone, Debug, Default)]
+pub struct EvaluationReport {
+    pub episodes : usize,
+    pub average_lifetime : f64,
+    pub min_lifetime : usize,
+    pub max_lifetime : usize,
+    /// Mean per step reward, averaged over the episodes.
+    pub average_reward : f64,
+    pub action_counts : Vec<usize>
+}
+
+impl fmt::Display for EvaluationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Episodes : {}", self.episodes)?;
+        writeln!(f, "Average Lifetime : {} (min {}, max {})", self.average_lifetime, self.min_lifetime, self.max_lifetime)?;
+        writeln!(f, "Average Total Reward : {}", self.average_reward)?;
+        write!(f, "Action counts : {:?}", self.action_counts)
+    }
+}
+
+/// Plays `iterations` episodes of `agent`, picking actions with `choose`.
+///
+/// `choose` receives the agent, its encoded state and the step index within
+/// the current episode, so stateful controllers can tell episodes apart.
+pub fn evaluate<A, F>(agent : &mut A, iterations : usize, nb_actions : usize, mut choose : F) -> EvaluationReport
+where A : Agent, F : FnMut(&A, &State, usize) -> usize {
+    println!("Evaluating model");
+    let percent_step = (iterations / 10).max(1);
+    let mut report = EvaluationReport {
+        episodes : iterations,
+        min_lifetime : usize::MAX,
+        action_counts : vec![0; nb_actions],
+        ..Default::default()
+    };
+
+    for i in 0..iterations {
+        let mut current_state = agent.reset();
+        let mut lifetime_reward = 0.0;
+        let mut finished = false;
+        let mut lifetime = 0;
+
+        while !finished {
+            let action = choose(agent, &current_state, lifetime);
+            report.action_counts[action] += 1;
+            let (next_state, reward, dead) = agent.simulate_action(action);
+            current_state = next_state;
+            finished = dead || agent.truncated();
+            lifetime += 1;
+            lifetime_reward += reward;
+        }
+
+        if i%percent_step == 0 {
+            println!("Completion : {}%", i as f64 * 100.0 / iterations as f64)
+        }
+
+        report.min_lifetime = report.min_lifetime.min(lifetime);
+        report.max_lifetime = report.max_lifetime.max(lifetime);
+        report.average_lifetime += lifetime as f64 / iterations as f64;
+        report.average_reward += lifetime_reward / (lifetime as f64 * iterations as f64);
+    }
+
+    if iterations == 0 {
+        report.min_lifetime = 0;
+    }
+    report
+}
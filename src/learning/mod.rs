@@ -1,2 +1,4 @@
+pub mod evaluation;
+pub mod multiagent;
 pub mod qlearning;
 pub mod deepqnet;
\ No newline at end of file
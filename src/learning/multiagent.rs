@@ -0,0 +1,107 @@
+use crate::learning::qlearning::{Agent, Policy, Transition};
+use crate::types::SimRng;
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PolicySharing {
+    /// Every agent reads and updates the same policy.
+    #[default]
+    Shared,
+    /// Each agent learns its own policy.
+    Independent
+}
+
+/// Q-learning of several agents living in the same environment at once.
+#[derive(Clone, Copy, Debug)]
+pub struct MultiAgentTraining {
+    pub iterations : usize,
+    pub alpha : f64,
+    pub gamma : f64,
+    pub epsilon : f64,
+    pub sharing : PolicySharing,
+    /// Every agent learns from the mean reward of the agents that acted
+    /// during the step instead of its own.
+    pub team_reward : bool
+}
+
+impl MultiAgentTraining {
+    pub fn nb_policies(&self, nb_agents : usize) -> usize {
+        match self.sharing {
+            PolicySharing::Shared => 1,
+            PolicySharing::Independent => nb_agents
+        }
+    }
+
+    /// Trains `policies` on `agents`, which must share one environment.
+    /// `reset_world` is called before each episode, after the agents reset,
+    /// to put the shared environment back in its initial state.
+    ///
+    /// Returns the average per step reward of each agent.
+    pub fn train<A : Agent, F : FnMut()>(&self, policies : &mut [Policy], agents : &mut [A], mut reset_world : F, rng : &mut SimRng) -> Vec<f64> {
+        assert_eq!(policies.len(), self.nb_policies(agents.len()));
+        assert!(policies.iter().all(|policy| !policy.qtable.is_empty()));
+        let nb_actions = policies[0].qtable[0].len();
+        let policy_of = |agent : usize| match self.sharing {
+            PolicySharing::Shared => 0,
+            PolicySharing::Independent => agent
+        };
+
+        println!("Multi-agent Training Begins");
+        let percent_step = (self.iterations / 100).max(1);
+        let mut average_reward = vec![0.0; agents.len()];
+
+        for i in 0..self.iterations {
+            let mut states : Vec<_> = agents.iter_mut().map(|agent| agent.reset()).collect();
+            reset_world();
+            let mut done = vec![false; agents.len()];
+            let mut lifetime_reward = vec![0.0; agents.len()];
+            let mut lifetime = vec![0; agents.len()];
+
+            while done.iter().any(|done| !done) {
+                let mut transitions = Vec::new();
+                for (agent, human) in agents.iter_mut().enumerate() {
+                    if done[agent] {
+                        continue;
+                    }
+                    let action = if rng.gen_range(0.0, 1.0) < self.epsilon {
+                        rng.gen_range(0, nb_actions)
+                    } else {
+                        policies[policy_of(agent)].predict_action(&states[agent])
+                    };
+                    let (next_state, reward, dead) = human.simulate_action(action);
+                    transitions.push((agent, Transition { state : states[agent], action, reward, next_state, done : dead }));
+                    lifetime_reward[agent] += reward;
+                    lifetime[agent] += 1;
+                    states[agent] = next_state;
+                    done[agent] = dead || human.truncated();
+                }
+
+                let team = transitions.iter().map(|(_, transition)| transition.reward).sum::<f64>()
+                    / transitions.len() as f64;
+                for (agent, mut transition) in transitions {
+                    if self.team_reward {
+                        transition.reward = team;
+                    }
+                    policies[policy_of(agent)].update(&transition, self.alpha, self.gamma);
+                }
+            }
+
+            for agent in 0..agents.len() {
+                average_reward[agent] += lifetime_reward[agent] / (lifetime[agent] as f64 * self.iterations as f64);
+            }
+
+            if i%percent_step == 0 {
+                println!("Completion : {}%", i as f64 * 100.0 / self.iterations as f64)
+            }
+        }
+
+        #[cfg(debug_assertions)]
+        {
+            println!("Multi-agent Training Finished");
+            println!("Training Average Total Reward per agent : {:?}", average_reward);
+        }
+        average_reward
+    }
+}
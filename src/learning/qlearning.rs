@@ -1,17 +1,28 @@
-use rand::{self, Rng};
+use crate::learning::evaluation::{self, EvaluationReport};
+use crate::types::SimRng;
 
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter};
+use std::path::Path;
+
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Policy {
-    pub(crate) qtable : Vec<Vec<f64>> // Reward table for each state and each action
+    pub(crate) qtable : Vec<Vec<f64>>, // Reward table for each state and each action
+    /// Number of updates of each state.
+    pub(crate) visits : Vec<u32>
 }
 
+#[allow(clippy::new_without_default, clippy::unnecessary_cast)]
 impl Policy {
     pub fn new() -> Policy {
-        Policy {qtable : Vec::new()}
+        Policy {qtable : Vec::new(), visits : Vec::new()}
     }
 
-    pub fn init(&mut self, nb_states : usize, nb_actions : usize) {
+    pub fn init(&mut self, nb_states : usize, nb_actions : usize, rng : &mut SimRng) {
         assert!(self.qtable.is_empty());
-        let mut rng = rand::thread_rng();
         self.qtable = (0..nb_states)
         .map(|_| 
             (0..nb_actions)
@@ -21,6 +32,17 @@ impl Policy {
             .collect()
         )
         .collect();
+        self.visits = vec![0; nb_states];
+    }
+
+    pub fn save<P : AsRef<Path>>(&self, path : P) -> io::Result<()> {
+        let writer = BufWriter::new(File::create(path)?);
+        bincode::serialize_into(writer, self).map_err(io::Error::other)
+    }
+
+    pub fn load<P : AsRef<Path>>(path : P) -> io::Result<Policy> {
+        let reader = BufReader::new(File::open(path)?);
+        bincode::deserialize_from(reader).map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
     }
 
     pub fn get_value(&self, state : &State, action : usize) -> f64 {
@@ -31,6 +53,16 @@ impl Policy {
         self.qtable[state.key][action] = value;
     }
 
+    /// Value of the best action in `state`.
+    pub fn max_value(&self, state : &State) -> f64 {
+        self.qtable[state.key].iter().copied().fold(f64::NEG_INFINITY, f64::max)
+    }
+
+    /// Number of times `state` was learnt from.
+    pub fn visits(&self, state : &State) -> u32 {
+        self.visits[state.key]
+    }
+
     pub fn predict_action(&self, state : &State) -> usize {
         self.qtable[state.key]
         .iter()
@@ -40,14 +72,32 @@ impl Policy {
         .unwrap().0
     }
 
-    pub fn train<A : Agent>(&mut self, agent : &mut A, iterations : usize, alpha : f64, gamma : f64, epsilon : f64) {
+    /// Q-learning update of the value of the transition's state and action.
+    /// Nothing follows a terminal state, so it is not bootstrapped from.
+    pub fn update(&mut self, transition : &Transition, alpha : f64, gamma : f64) {
+        let old_value = self.qtable[transition.state.key][transition.action];
+
+        let target = if transition.done {
+            transition.reward
+        } else {
+            let next_max = *self.qtable[transition.next_state.key].iter()
+            .max_by(|a,b| a.partial_cmp(b).unwrap())
+            .unwrap();
+            transition.reward + gamma * next_max
+        };
+
+        self.qtable[transition.state.key][transition.action] = (1.0 - alpha) * old_value 
+                + alpha * target;
+        self.visits[transition.state.key] = self.visits[transition.state.key].saturating_add(1);
+    }
+
+    pub fn train<A : Agent>(&mut self, agent : &mut A, iterations : usize, alpha : f64, gamma : f64, epsilon : f64, rng : &mut SimRng) {
         
-        let percent_step = iterations / 100;
+        let percent_step = (iterations / 100).max(1);
         println!("Training Begins");
         assert_ne!(self.qtable.len(), 0);
         let nb_actions = self.qtable[0].len();
         assert_ne!(nb_actions, 0);
-        let mut rng = rand::thread_rng();
 
         let mut average_reward = 0.0;
         let mut action_count = vec![0; self.qtable[0].len()];
@@ -69,20 +119,13 @@ impl Policy {
                 
                 action_count[action as usize] += 1;
                 
-                let old_value = self.qtable[current_state.key][action];
-
                 let (next_state, reward, dead) = agent.simulate_action(action);
                 lifetime_reward += reward;
 
-                let next_max = *self.qtable[next_state.key].iter()
-                .max_by(|a,b| a.partial_cmp(b).unwrap())
-                .unwrap();
-            
-                self.qtable[current_state.key][action] = (1.0 - alpha) * old_value 
-                        + alpha * (reward + gamma * next_max);
+                self.update(&Transition { state : current_state, action, reward, next_state, done : dead }, alpha, gamma);
 
                 current_state = next_state;
-                finished = dead;
+                finished = dead || agent.truncated();
                 lifetime +=1;
             }
             
@@ -105,54 +148,45 @@ impl Policy {
         }
     }
 
-    #[cfg(debug_assertions)]
-    pub fn evaluate<A : Agent>(&self, agent : &mut A, iterations : usize) {
-
-        println!("Evaluating model");
-        let mut average_lifetime = 0.0;
-        let mut average_reward = 0.0;
-        let mut action_count = vec![0; self.qtable[0].len()];
-        for i in 0..iterations {
-            let mut current_state = agent.reset();
-            let mut reward;
-            let mut lifetime_reward = 0.0;
-            let mut finished = false;
-            let mut lifetime = 0;
-
-            while !finished {
-                let action = self.predict_action(&current_state);
-                action_count[action as usize] += 1;
-                (current_state, reward, finished) = agent.simulate_action(action);
-                lifetime += 1;
-                lifetime_reward += reward;
-            }
-
-            if i%100 == 0 {
-                println!("Completion : {}%", i as f64 * 100.0 / iterations as f64)
-            }
-
-            average_lifetime += lifetime as f64 / iterations as f64;
-            average_reward += lifetime_reward as f64 / (lifetime as f64 * iterations as f64);
-        }
-        println!("Average Lifetime : {average_lifetime}");
-        println!("Average Total Reward : {average_reward}");
-        println!("Action counts : {:?}", action_count);
+    pub fn evaluate<A : Agent>(&self, agent : &mut A, iterations : usize) -> EvaluationReport {
+        evaluation::evaluate(agent, iterations, self.qtable[0].len(), 
+            |_, state, _| self.predict_action(state))
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct State {
     pub key : usize
 }
 
+/// One step of experience : the agent took `action` in `state`, received
+/// `reward` and ended up in `next_state`.
+#[derive(Clone, Copy, Debug)]
+pub struct Transition {
+    pub state : State,
+    pub action : usize,
+    pub reward : f64,
+    pub next_state : State,
+    pub done : bool
+}
+
 pub trait Agent {
     // Learning
     fn reset(&mut self) -> State;
+    /// Carries out `action` and lets time pass, returns the state reached,
+    /// the reward of the step and whether the agent died.
     fn simulate_action(&mut self, action : usize) -> (State, f64, bool);
+    /// Whether the episode is cut short while the agent lives on. The last
+    /// step is then learnt from as any other, not as a terminal one.
+    fn truncated(&self) -> bool {
+        false
+    }
     fn simulation_step_time(&mut self);
     fn compute_reward(&self) -> f64;
 
     // Execution
     fn choose_action(&self) -> usize;
-    fn do_action(&mut self, action : usize);
+    /// Carries out `action` and returns its immediate reward.
+    fn do_action(&mut self, action : usize) -> f64;
     fn step(&mut self);
 }
@@ -1,4 +1,5 @@
 pub mod simulation;
 pub mod types;
 pub mod display;
-pub mod learning;
\ No newline at end of file
+pub mod learning;
+pub mod scenario;
\ No newline at end of file
@@ -1,80 +1,749 @@
-use brains::simulation::{
-        actors:: { behaviour::QLBehaviour,
-                    humans::Human},
-        world::World};
-            
+use brains::scenario::{LearnedBehaviours, Scenario};
+#[cfg(feature = "graphics")]
+use brains::display::camera::{self, Camera};
+#[cfg(feature = "graphics")]
+use brains::display::charts::{self, ChartPanel};
+#[cfg(feature = "graphics")]
 use brains::display::draw::Drawable;
+#[cfg(feature = "graphics")]
+use brains::display::editor::Editor;
+use brains::display::export::FrameExporter;
+use brains::display::raster;
+#[cfg(feature = "graphics")]
+use brains::display::hud::{self, TickRate};
+#[cfg(feature = "graphics")]
+use brains::display::overlay::PolicyOverlay;
+#[cfg(feature = "graphics")]
+use brains::display::palette;
+#[cfg(feature = "graphics")]
+use brains::display::replay::ReplayViewer;
+#[cfg(feature = "graphics")]
+use brains::display::sprites::SpriteSet;
+#[cfg(feature = "graphics")]
+use brains::display::trails::TrailOverlay;
+use brains::display::terminal::TerminalRenderer;
+use brains::simulation::events::Event;
+#[cfg(feature = "graphics")]
+use brains::simulation::events::EventKind;
+use brains::simulation::headless::HeadlessRunner;
+#[cfg(feature = "graphics")]
+use brains::simulation::render::{FrameBuffer, FramePublisher};
+use brains::simulation::replay::Recorder;
+use brains::simulation::replay::{HumanFrame, Replay, ReplayPlayer};
+use brains::simulation::scheduler::Scheduler;
+use brains::simulation::snapshot::WorldSnapshot;
+use brains::simulation::statistics::{StatisticsCollector, TickStats};
+use brains::simulation::trajectory::Trajectory;
+#[cfg(feature = "graphics")]
+use brains::simulation::trajectory::Trails;
+use brains::simulation::world::World;
+#[cfg(feature = "graphics")]
 use brains::types::Position;
 
+use clap::{Args, Parser, Subcommand};
+#[cfg(feature = "graphics")]
 use piston_window::{PistonWindow, WindowSettings};
+#[cfg(feature = "graphics")]
 use piston_window::*;
 
-use std::sync::{Arc, Mutex, RwLock};
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
 use std::thread;
 use std::time::Duration;
 
-static TIME_STEP : Duration = Duration::from_millis(200);
-fn main() {
-    let my_world = Arc::new(Mutex::new(World::new(20,20,10)));
-    let behaviour = Arc::new(RwLock::new(QLBehaviour::new()));
-    {
-        let mut world_data = my_world.lock().unwrap();
-        world_data.add_forest( Position{x : 12, y : 1}, Position{x : 18, y : 4});
-        world_data.add_lake( Position{x : 1, y : 15}, Position{x : 3, y : 19});
-    }
-    
-    {
-        let world_data = my_world.lock().unwrap();
-        let mut train_human = Human::new(0, 0, behaviour.clone(), world_data.environment.clone());
-        behaviour.write().unwrap()
-        .train(&mut train_human, 100000, 0.2, 0.6, 0.8);
-    }
-    
-    {
-        
-        let world_data = my_world.lock().unwrap();
-        let mut test_human = Human::new(15, 18, behaviour.clone(), world_data.environment.clone());
-        behaviour.read().unwrap().evaluate(&mut test_human, 1000);
-    }
-
-    {
-        let mut world_data = my_world.lock().unwrap();
-        let new_human = Human::new(15, 18, behaviour.clone(), world_data.environment.clone());
-        world_data.add_human(new_human);
-        let new_human = Human::new(6, 5, behaviour.clone(), world_data.environment.clone());
-        world_data.add_human(new_human);
-        let new_human = Human::new(6, 5, behaviour.clone(), world_data.environment.clone());
-        world_data.add_human(new_human);
-        let new_human = Human::new(5, 13, behaviour.clone(), world_data.environment.clone());
-        world_data.add_human(new_human);
-        
-    }
-    
-    let simulation_world = Arc::clone(&my_world);
-    let simulation_thread = thread::spawn(move || {
-        loop {
-            {
-                let mut world = simulation_world.lock().unwrap();
-                world.step_time();
+/// Length of a headless run when neither the command line nor the scenario
+/// gives one.
+const DEFAULT_TICKS : u64 = 1000;
+/// Time between two frames of a recording in the terminal, as in the replay
+/// window at normal speed.
+const REPLAY_TIME_STEP : Duration = Duration::from_millis(100);
+
+#[derive(Parser)]
+#[command(about = "Humans learning to survive in a small world")]
+struct Cli {
+    #[command(subcommand)]
+    command : Command
+}
+
+#[derive(Args)]
+struct Common {
+    /// Scenario file, TOML or JSON.
+    #[arg(long, default_value = "scenarios/default.toml")]
+    scenario : PathBuf,
+    /// Overrides the seed of the scenario.
+    #[arg(long)]
+    seed : Option<u64>
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Trains the Q-learning behaviours of the scenario and saves their policies.
+    Train {
+        #[command(flatten)]
+        common : Common,
+        #[arg(long, default_value = "policy.bin")]
+        output : PathBuf
+    },
+    /// Evaluates every behaviour of the scenario.
+    Evaluate {
+        #[command(flatten)]
+        common : Common,
+        /// Policies written by `train`, needed by Q-learning behaviours.
+        #[arg(long)]
+        policy : Option<PathBuf>,
+        /// Writes the report to this file instead of printing it.
+        #[arg(long)]
+        output : Option<PathBuf>,
+        /// Writes the paths of the first evaluation lives to this file, as
+        /// JSON lines, or drawn over the map if it ends in `.png`.
+        #[arg(long)]
+        trajectories : Option<PathBuf>,
+        /// Number of lives of each behaviour in `--trajectories`.
+        #[arg(long, default_value_t = 10)]
+        trajectory_episodes : usize
+    },
+    /// Runs the scenario without a window and dumps statistics.
+    Simulate {
+        #[command(flatten)]
+        common : Common,
+        #[arg(long)]
+        policy : Option<PathBuf>,
+        /// Overrides the number of ticks of the scenario.
+        #[arg(long)]
+        ticks : Option<u64>,
+        /// Milliseconds between two ticks, as fast as possible if missing.
+        #[arg(long)]
+        time_step_ms : Option<u64>,
+        /// Stops once every human is dead.
+        #[arg(long)]
+        stop_when_extinct : bool,
+        /// Reads commands from the standard input to pause, step, change the
+        /// speed or quit the run. It pauses once `--ticks` are done.
+        #[arg(long)]
+        interactive : bool,
+        /// Snapshot written by `--save` to resume instead of starting anew.
+        #[arg(long)]
+        resume : Option<PathBuf>,
+        /// Writes a snapshot of the world at the end of the run.
+        #[arg(long)]
+        save : Option<PathBuf>,
+        /// Records every tick of the run to this file, for `replay`.
+        #[arg(long)]
+        record : Option<PathBuf>,
+        /// Writes the events of the run to this file, one JSON object per line.
+        #[arg(long)]
+        events : Option<PathBuf>,
+        /// CSV file receiving the statistics of every tick.
+        #[arg(long)]
+        output : Option<PathBuf>
+    },
+    /// Renders a recording, or a headless run of the scenario, to images.
+    Render {
+        #[command(flatten)]
+        common : Common,
+        #[arg(long)]
+        policy : Option<PathBuf>,
+        /// Recording made by `simulate --record` to render instead of running
+        /// the scenario.
+        #[arg(long)]
+        replay : Option<PathBuf>,
+        /// Stops after this many ticks, the whole recording or the ticks of
+        /// the scenario if missing.
+        #[arg(long)]
+        ticks : Option<u64>,
+        #[arg(long)]
+        stop_when_extinct : bool,
+        /// Renders one tick out of this many, the last one always.
+        #[arg(long, default_value_t = 1)]
+        every : u64,
+        /// Size in pixels of a cell, the one of the world if missing.
+        #[arg(long)]
+        cell_size : Option<u32>,
+        /// Milliseconds each frame of a GIF is shown.
+        #[arg(long, default_value_t = 100)]
+        delay_ms : u64,
+        /// `.png` for the last frame, `.gif` for an animation, or a directory
+        /// receiving one PNG per frame.
+        output : PathBuf
+    },
+    /// Shows the running scenario, or a recording, in the terminal.
+    Terminal {
+        #[command(flatten)]
+        common : Common,
+        #[arg(long)]
+        policy : Option<PathBuf>,
+        /// Recording made by `simulate --record` to play instead of running
+        /// the scenario.
+        #[arg(long)]
+        replay : Option<PathBuf>,
+        /// Stops after this many ticks, the ticks of the scenario or the whole
+        /// recording if missing.
+        #[arg(long)]
+        ticks : Option<u64>,
+        #[arg(long)]
+        stop_when_extinct : bool,
+        /// Milliseconds between two frames, the time step of the scenario or
+        /// 100 for recordings if missing.
+        #[arg(long)]
+        time_step_ms : Option<u64>,
+        /// Draws with characters only, for terminals without 24 bit colours.
+        #[arg(long)]
+        no_color : bool
+    },
+    /// Opens a window on the running scenario.
+    #[cfg(feature = "graphics")]
+    View {
+        #[command(flatten)]
+        common : Common,
+        #[arg(long)]
+        policy : Option<PathBuf>,
+        /// Map file written by the editor.
+        #[arg(long, default_value = "map.txt")]
+        map_output : PathBuf,
+        /// Number of positions in the trails of the humans.
+        #[arg(long, default_value_t = 30)]
+        trail_length : usize
+    },
+    /// Plays back a recording made by `simulate --record`.
+    #[cfg(feature = "graphics")]
+    Replay {
+        file : PathBuf,
+        /// Size in pixels of a cell, the one of the recorded world if missing.
+        #[arg(long)]
+        cell_size : Option<f64>
+    }
+}
+
+fn load_scenario(common : &Common) -> io::Result<Scenario> {
+    let mut scenario = Scenario::load(&common.scenario)?;
+    if common.seed.is_some() {
+        scenario.seed = common.seed;
+    }
+    Ok(scenario)
+}
+
+fn load_learned(scenario : &Scenario, policy : &Option<PathBuf>) -> io::Result<LearnedBehaviours> {
+    match policy {
+        Some(path) => scenario.load_learned(path),
+        None => Ok(LearnedBehaviours::new())
+    }
+}
+
+fn train(common : &Common, output : &PathBuf) -> io::Result<()> {
+    let scenario = load_scenario(common)?;
+    let world = scenario.build_world()?;
+    let learned = scenario.train(&world);
+    Scenario::save_learned(&learned, output)?;
+    println!("Policies saved to {}", output.display());
+    Ok(())
+}
+
+fn evaluate(common : &Common, policy : &Option<PathBuf>, output : &Option<PathBuf>, trajectories : &Option<(PathBuf, usize)>) -> io::Result<()> {
+    let scenario = load_scenario(common)?;
+    let world = scenario.build_world()?;
+    let learned = load_learned(&scenario, policy)?;
+
+    if let Some((path, episodes)) = trajectories {
+        let traced = scenario.trajectories(&world, &learned, *episodes)?;
+        if path.extension().is_some_and(|extension| extension == "png") {
+            let cells = &world.environment.read().unwrap().cells;
+            raster::render_trajectories(cells, &traced, world.cell_size as u32).save(path).map_err(io::Error::other)?;
+        } else {
+            Trajectory::write_json_lines(&traced, &mut BufWriter::new(File::create(path)?))?;
+        }
+        println!("{} trajectories written to {}", traced.len(), path.display());
+    }
+
+    let mut report = String::new();
+    for (name, evaluation) in scenario.evaluate(&world, &learned)? {
+        report += &format!("== {name} ==\n{evaluation}\n");
+    }
+    match output {
+        Some(path) => fs::write(path, report),
+        None => {
+            print!("{report}");
+            Ok(())
+        }
+    }
+}
+
+struct RunFiles {
+    resume : Option<PathBuf>,
+    save : Option<PathBuf>,
+    record : Option<PathBuf>,
+    events : Option<PathBuf>,
+    output : Option<PathBuf>
+}
+
+/// Events of a run written as JSON, one per line.
+struct EventLog {
+    writer : BufWriter<File>,
+    // First error met while writing, reported at the end.
+    written : io::Result<()>
+}
+
+impl EventLog {
+    fn write(&mut self, event : &Event) {
+        if self.written.is_ok() {
+            self.written = serde_json::to_writer(&mut self.writer, event).map_err(io::Error::from)
+                .and_then(|_| writeln!(self.writer));
+        }
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        std::mem::replace(&mut self.written, Ok(()))?;
+        self.writer.flush()
+    }
+}
+
+/// What `simulate` produces along the run.
+struct Outputs {
+    statistics : StatisticsCollector,
+    events : Option<Arc<Mutex<EventLog>>>,
+    csv : Option<BufWriter<File>>,
+    recorder : Option<Recorder>,
+    start_tick : u64,
+    deaths_hunger : usize,
+    deaths_thirst : usize,
+    // First error met while writing the CSV, reported at the end.
+    written : io::Result<()>
+}
+
+impl Outputs {
+    fn new(world : &mut World, files : &RunFiles) -> io::Result<Outputs> {
+        let events = match &files.events {
+            Some(path) => {
+                let log = Arc::new(Mutex::new(EventLog { writer : BufWriter::new(File::create(path)?), written : Ok(()) }));
+                let subscribed = log.clone();
+                world.events.subscribe(move |event : &Event| subscribed.lock().unwrap().write(event));
+                Some(log)
+            },
+            None => None
+        };
+        let csv = match &files.output {
+            Some(path) => {
+                let mut csv = BufWriter::new(File::create(path)?);
+                TickStats::write_csv_header(&mut csv)?;
+                Some(csv)
+            },
+            None => None
+        };
+        Ok(Outputs {
+            // Rows are written as they come, only the last sample is kept.
+            statistics : StatisticsCollector::attach(world, Some(1)),
+            events,
+            csv,
+            recorder : files.record.as_ref().map(|_| Recorder::new(world)),
+            start_tick : world.tick,
+            deaths_hunger : 0,
+            deaths_thirst : 0,
+            written : Ok(())
+        })
+    }
+
+    fn tick(&mut self, world : &World) {
+        if let Some(recorder) = self.recorder.as_mut() {
+            recorder.record(world);
+        }
+        let stats = self.statistics.sample(world);
+        self.deaths_hunger += stats.deaths_hunger;
+        self.deaths_thirst += stats.deaths_thirst;
+        if let (Some(csv), Ok(())) = (self.csv.as_mut(), &self.written) {
+            self.written = stats.write_csv_row(csv);
+        }
+    }
+
+    fn finish(mut self, world : &World, files : &RunFiles) -> io::Result<()> {
+        self.written?;
+        if let Some(csv) = self.csv.as_mut() {
+            csv.flush()?;
+        }
+        if let Some(events) = &self.events {
+            events.lock().unwrap().finish()?;
+        }
+        if let Some(path) = &files.save {
+            WorldSnapshot::capture(world).save(path)?;
+        }
+        if let (Some(recorder), Some(path)) = (self.recorder, &files.record) {
+            recorder.finish().save(path)?;
+        }
+
+        let stats = self.statistics.sample(world);
+        println!("Ticks : {}", world.tick - self.start_tick);
+        println!("Alive : {} / {}", stats.alive, world.humans.len());
+        println!("Deaths : {} of hunger, {} of thirst", self.deaths_hunger, self.deaths_thirst);
+        println!("Average hunger : {}, thirst : {}, energy : {}", stats.hunger.mean, stats.thirst.mean, stats.energy.mean);
+        Ok(())
+    }
+}
+
+/// Reads scheduler commands from the standard input until `quit` or its end.
+fn control_from_stdin(scheduler : &Scheduler) -> io::Result<()> {
+    println!("Commands : pause, resume, step [N], speed MS, status, quit");
+    for line in io::stdin().lines() {
+        let line = line?;
+        let words : Vec<&str> = line.split_whitespace().collect();
+        match words.as_slice() {
+            ["pause"] => scheduler.pause(),
+            ["resume"] => scheduler.resume(),
+            ["step"] => scheduler.step(1),
+            ["step", steps] => match steps.parse() {
+                Ok(steps) => scheduler.step(steps),
+                Err(_) => println!("Invalid number of steps : {steps}")
+            },
+            ["speed", time_step] => match time_step.parse() {
+                Ok(time_step) => scheduler.set_time_step(Duration::from_millis(time_step)),
+                Err(_) => println!("Invalid time step : {time_step}")
+            },
+            ["status"] => println!("{:?}", scheduler.status()),
+            ["quit"] => break,
+            [] => (),
+            _ => println!("Unknown command : {line}")
+        }
+    }
+    Ok(())
+}
+
+fn simulate(common : &Common, policy : &Option<PathBuf>, runner : HeadlessRunner, interactive : bool, files : &RunFiles) -> io::Result<()> {
+    let scenario = load_scenario(common)?;
+    let learned = load_learned(&scenario, policy)?;
+    let mut world = match &files.resume {
+        Some(path) => scenario.restore(&WorldSnapshot::load(path)?, &learned)?,
+        None => {
+            let mut world = scenario.build_world()?;
+            scenario.populate(&mut world, &learned)?;
+            world
+        }
+    };
+    let mut outputs = Outputs::new(&mut world, files)?;
+
+    let ticks = runner.ticks.or(scenario.run.ticks);
+    if interactive {
+        let outputs = Arc::new(Mutex::new(outputs));
+        let ticked = outputs.clone();
+        let time_step = runner.time_step.unwrap_or(scenario.time_step());
+        let pause_at = ticks.map(|ticks| world.tick + ticks);
+        let scheduler = Scheduler::start(world, time_step, false, pause_at, move |world| ticked.lock().unwrap().tick(world));
+        control_from_stdin(&scheduler)?;
+        let world = scheduler.shutdown();
+        let outputs = Arc::try_unwrap(outputs).ok().unwrap().into_inner().unwrap();
+        outputs.finish(&world, files)
+    } else {
+        let runner = HeadlessRunner { ticks : ticks.or(Some(DEFAULT_TICKS)), ..runner };
+        runner.run(&mut world, |_, world| outputs.tick(world));
+        outputs.finish(&world, files)
+    }
+}
+
+struct RenderOptions {
+    replay : Option<PathBuf>,
+    ticks : Option<u64>,
+    stop_when_extinct : bool,
+    every : u64,
+    cell_size : Option<u32>,
+    delay : Duration
+}
+
+fn render(common : &Common, policy : &Option<PathBuf>, options : &RenderOptions, output : &PathBuf) -> io::Result<()> {
+    let every = options.every.max(1);
+    let frames = match &options.replay {
+        Some(path) => {
+            let replay = Replay::load(path)?;
+            let mut exporter = FrameExporter::create(output, options.cell_size.unwrap_or(replay.cell_size as u32), options.delay)?;
+            let mut player = ReplayPlayer::new(replay);
+            let last = options.ticks.map_or(usize::MAX, |ticks| ticks as usize).min(player.nb_frames() - 1);
+            for frame in 0..=last {
+                player.seek(frame);
+                if (frame as u64).is_multiple_of(every) || frame == last {
+                    exporter.add(player.cells(), player.humans())?;
+                }
+            }
+            exporter.finish()?
+        },
+        None => {
+            let scenario = load_scenario(common)?;
+            let learned = load_learned(&scenario, policy)?;
+            let mut world = scenario.build_world()?;
+            scenario.populate(&mut world, &learned)?;
+            let mut exporter = FrameExporter::create(output, options.cell_size.unwrap_or(world.cell_size as u32), options.delay)?;
+            let add = |exporter : &mut FrameExporter, world : &World| {
+                let humans : Vec<HumanFrame> = world.humans.iter().map(HumanFrame::new).collect();
+                exporter.add(&world.environment.read().unwrap().cells, &humans)
+            };
+
+            add(&mut exporter, &world)?;
+            let runner = HeadlessRunner {
+                ticks : options.ticks.or(scenario.run.ticks).or(Some(DEFAULT_TICKS)),
+                time_step : None,
+                stop_when_extinct : options.stop_when_extinct
+            };
+            // The first error stops the rendering, not the run.
+            let mut written = Ok(());
+            let mut last_added = 0;
+            let ran = runner.run(&mut world, |tick, world| {
+                if tick.is_multiple_of(every) && written.is_ok() {
+                    written = add(&mut exporter, world);
+                    last_added = tick;
+                }
+            });
+            written?;
+            if last_added != ran {
+                add(&mut exporter, &world)?;
             }
-            thread::sleep(TIME_STEP);
+            exporter.finish()?
+        }
+    };
+    println!("{} frames rendered to {}", frames, output.display());
+    Ok(())
+}
+
+struct TerminalOptions {
+    replay : Option<PathBuf>,
+    ticks : Option<u64>,
+    stop_when_extinct : bool,
+    time_step : Option<Duration>,
+    colors : bool
+}
+
+fn terminal(common : &Common, policy : &Option<PathBuf>, options : &TerminalOptions) -> io::Result<()> {
+    let mut renderer = TerminalRenderer::new(options.colors);
+    let mut out = io::stdout().lock();
+    match &options.replay {
+        Some(path) => {
+            let mut player = ReplayPlayer::new(Replay::load(path)?);
+            let last = options.ticks.map_or(usize::MAX, |ticks| ticks as usize).min(player.nb_frames() - 1);
+            let time_step = options.time_step.unwrap_or(REPLAY_TIME_STEP);
+            for frame in 0..=last {
+                player.seek(frame);
+                renderer.draw(&mut out, player.tick(), player.cells(), player.humans())?;
+                thread::sleep(time_step);
+            }
+        },
+        None => {
+            let scenario = load_scenario(common)?;
+            let learned = load_learned(&scenario, policy)?;
+            let mut world = scenario.build_world()?;
+            scenario.populate(&mut world, &learned)?;
+            let draw = |renderer : &mut TerminalRenderer, out : &mut io::StdoutLock, world : &World| {
+                let humans : Vec<HumanFrame> = world.humans.iter().map(HumanFrame::new).collect();
+                renderer.draw(out, world.tick, &world.environment.read().unwrap().cells, &humans)
+            };
+
+            draw(&mut renderer, &mut out, &world)?;
+            let runner = HeadlessRunner {
+                ticks : options.ticks.or(scenario.run.ticks),
+                time_step : Some(options.time_step.unwrap_or(scenario.time_step())),
+                stop_when_extinct : options.stop_when_extinct
+            };
+            let mut written = Ok(());
+            runner.run(&mut world, |_, world| {
+                if written.is_ok() {
+                    written = draw(&mut renderer, &mut out, world);
+                }
+            });
+            written?;
         }
+    }
+    Ok(())
+}
+
+#[cfg(feature = "graphics")]
+fn view(common : &Common, policy : &Option<PathBuf>, map_output : PathBuf, trail_length : usize) -> io::Result<()> {
+    let scenario = load_scenario(common)?;
+    let mut world = scenario.build_world()?;
+    let learned = load_learned(&scenario, policy)?;
+    scenario.populate(&mut world, &learned)?;
+    let cell_size = world.cell_size as f64;
+    let (width, height) = {
+        let cells = &world.environment.read().unwrap().cells;
+        (cells.width(), cells.height())
+    };
+    let mut overlay = PolicyOverlay::new(&learned, world.environment.clone());
+
+    // The simulation publishes a frame after each step, the window draws the
+    // latest one without ever waiting on the world.
+    let frames = Arc::new(FrameBuffer::default());
+    let mut publisher = FramePublisher::new(frames.clone());
+    publisher.publish(&world);
+    let mut frame = frames.take().unwrap();
+    let edits = FramePublisher::new(frames.clone());
+    let mut statistics = StatisticsCollector::attach(&mut world, Some(charts::CHART_HISTORY));
+    statistics.sample(&world);
+    let statistics = Arc::new(Mutex::new(statistics));
+    let mut charts = ChartPanel::new(statistics.clone());
+    let mut trails = Trails::new(trail_length);
+    trails.record(&world);
+    let trails = Arc::new(Mutex::new(trails));
+    // Trails follow the humans by index, the editor can take one out.
+    let removals = trails.clone();
+    world.events.subscribe(move |event : &Event| if let EventKind::HumanRemoved { .. } = event.kind {
+        removals.lock().unwrap().remove(event.human);
     });
+    let mut trail_overlay = TrailOverlay::new(trails.clone());
+    let scheduler = Scheduler::start(world, scenario.time_step(), false, scenario.run.ticks,
+        move |world| {
+            publisher.publish(world);
+            statistics.lock().unwrap().sample(world);
+            trails.lock().unwrap().record(world);
+        });
+    let mut editor = Editor::new(&scenario, &learned, &scheduler, edits, map_output);
 
+    let map_size = [width as f64 * cell_size, height as f64 * cell_size];
+    let mut window: PistonWindow =
+    WindowSettings::new("My small world", camera::window_size(map_size))
+        .build().unwrap();
+
+    let mut glyphs = hud::load_font(&mut window)?;
+    let sprites = SpriteSet::load(&mut window);
+    let mut use_sprites = !sprites.is_empty();
+
+    // Space pauses, right steps once, up and down speed the simulation up
+    // or slow it down. Dragging pans, scrolling zooms and home resets the view.
+    // Clicking a human inspects it, escape lets go of it. O, T, H and B
+    // control the policy overlay, E opens the editor, G switches between
+    // sprites and flat shapes, C shows the charts and P the trails.
+    let mut camera = Camera::new(map_size, window.size().into());
+    let mut rate = TickRate::default();
+    let mut selected : Option<usize> = None;
+    let (mut cursor, mut pressed_at) = ([0.0; 2], [0.0; 2]);
+    let mut title = String::new();
+    while let Some(e) = window.next() {
+        // The mouse edits the world in the editor, instead of panning.
+        if editor.active {
+            camera.resize_event(&e);
+            camera.zoom_event(&e);
+        } else {
+            camera.event(&e);
+        }
+        editor.event(&e, |position| {
+            let [x, y] = camera.to_world(position);
+            Position::new((x / cell_size).floor() as i32, (y / cell_size).floor() as i32)
+        });
+        if let Some(position) = e.mouse_cursor_args() {
+            cursor = position;
+        }
+        if let Some(Button::Mouse(MouseButton::Left)) = e.press_args() {
+            pressed_at = cursor;
+        }
+        // A release where the button was pressed is a click, not a drag.
+        if let Some(Button::Mouse(MouseButton::Left)) = e.release_args() {
+            if !editor.active && (cursor[0] - pressed_at[0]).abs() + (cursor[1] - pressed_at[1]).abs() < 4.0 {
+                let [x, y] = camera.to_world(cursor);
+                selected = hud::human_at(&frame, (x / cell_size).floor() as i32, (y / cell_size).floor() as i32);
+            }
+        }
+        if let Some(Button::Keyboard(key)) = e.press_args() {
+            match key {
+                _ if overlay.key(key) || editor.key(key) || charts.key(key) || trail_overlay.key(key) => (),
+                Key::E => {
+                    editor.toggle(&scheduler);
+                    selected = None;
+                },
+                Key::Space if !editor.active => scheduler.toggle_pause(),
+                Key::Right if !editor.active => scheduler.step(1),
+                Key::Up => scheduler.speed_up(),
+                Key::Down => scheduler.slow_down(),
+                Key::Home => camera.reset(),
+                Key::Escape => selected = None,
+                Key::G => use_sprites = !use_sprites,
+                _ => ()
+            }
+        }
+
+        if let Some(latest) = frames.take() {
+            frame = latest;
+            rate.sample(frame.tick);
+        }
+        overlay.update();
+        charts.update();
+        trail_overlay.update(frame.tick);
+        let status = scheduler.status();
+        let current = format!("My small world - tick {} - {} alive - {} ms{}",
+            frame.tick, frame.alive(), status.time_step.as_millis(), if status.paused { " - paused" } else { "" });
+        if current != title {
+            window.set_title(current.clone());
+            title = current;
+        }
+
+        window.draw_2d(&e, |c, g, device| {
+            clear(palette::BACKGROUND, g);
+            if use_sprites {
+                sprites.draw_world(&frame.cells, &frame.humans, cell_size, camera.transform(c), g);
+            } else {
+                frame.draw(0.0, 0.0, cell_size, camera.transform(c), g);
+            }
+            overlay.draw(cell_size, camera.transform(c), g);
+            trail_overlay.draw(cell_size, camera.transform(c), g);
+            let mut lines = hud::status_lines(&frame, &status, rate.rate());
+            lines.extend(overlay.description());
+            lines.extend(editor.description());
+            hud::text_box(&lines, [4.0, 4.0], &mut glyphs, c, g);
+            charts.draw(&mut glyphs, c, g);
+            if let Some(index) = selected {
+                // The inspector moves left of the charts.
+                let right = if charts.visible { charts::PANEL_WIDTH + 4.0 } else { 4.0 };
+                hud::highlight(&frame, index, cell_size, camera.transform(c), g);
+                hud::text_box(&hud::inspector_lines(&frame, index), [-right, 4.0], &mut glyphs, c, g);
+            }
+            glyphs.factory.encoder.flush(device);
+        });
+    }
+
+    scheduler.shutdown();
+    println!("Simulation Done");
+    Ok(())
+}
+
+#[cfg(feature = "graphics")]
+fn replay(file : &PathBuf, cell_size : Option<f64>) -> io::Result<()> {
+    let replay = Replay::load(file)?;
+    let cell_size = cell_size.unwrap_or(replay.cell_size as f64);
+    let mut viewer = ReplayViewer::new(ReplayPlayer::new(replay), cell_size);
 
     let mut window: PistonWindow =
-    WindowSettings::new("My small world", [200; 2])
+    WindowSettings::new(viewer.title(), viewer.window_size())
         .build().unwrap();
+    let sprites = SpriteSet::load(&mut window);
 
+    let mut title = viewer.title();
     while let Some(e) = window.next() {
-        let world = my_world.lock().unwrap();
+        viewer.event(&e);
+        if viewer.title() != title {
+            title = viewer.title();
+            window.set_title(title.clone());
+        }
         window.draw_2d(&e, |c, g, _| {
-            clear([0.4, 0.8, 0.5, 1.0], g);
-            world.draw(0.0, 0.0, 10.0, c, g);
+            clear(palette::BACKGROUND, g);
+            viewer.draw(&sprites, c, g);
         });
     }
+    Ok(())
+}
 
-    simulation_thread.join().unwrap();
-
-    println!("Simulation Done");
-}
\ No newline at end of file
+fn main() -> io::Result<()> {
+    match Cli::parse().command {
+        Command::Train { common, output } => train(&common, &output),
+        Command::Evaluate { common, policy, output, trajectories, trajectory_episodes } => {
+            let trajectories = trajectories.map(|path| (path, trajectory_episodes));
+            evaluate(&common, &policy, &output, &trajectories)
+        },
+        Command::Simulate { common, policy, ticks, time_step_ms, stop_when_extinct, interactive, resume, save, record, events, output } => {
+            let runner = HeadlessRunner { ticks, time_step : time_step_ms.map(Duration::from_millis), stop_when_extinct };
+            simulate(&common, &policy, runner, interactive, &RunFiles { resume, save, record, events, output })
+        },
+        Command::Render { common, policy, replay, ticks, stop_when_extinct, every, cell_size, delay_ms, output } => {
+            let options = RenderOptions { replay, ticks, stop_when_extinct, every, cell_size, delay : Duration::from_millis(delay_ms) };
+            render(&common, &policy, &options, &output)
+        },
+        Command::Terminal { common, policy, replay, ticks, stop_when_extinct, time_step_ms, no_color } => {
+            let options = TerminalOptions { replay, ticks, stop_when_extinct, time_step : time_step_ms.map(Duration::from_millis), colors : !no_color };
+            terminal(&common, &policy, &options)
+        },
+        #[cfg(feature = "graphics")]
+        Command::View { common, policy, map_output, trail_length } => view(&common, &policy, map_output, trail_length),
+        #[cfg(feature = "graphics")]
+        Command::Replay { file, cell_size } => replay(&file, cell_size)
+    }
+}
@@ -0,0 +1,577 @@
+use crate::learning::evaluation::EvaluationReport;
+use crate::learning::multiagent::{MultiAgentTraining, PolicySharing};
+use crate::learning::qlearning::Policy;
+use crate::simulation::actors::behaviour::{OnlineLearning, QLBehaviour, SharedBehaviour};
+use crate::simulation::actors::behaviour_tree::BTBehaviour;
+use crate::simulation::actors::goap::{GoapBehaviour, Planner};
+use crate::simulation::actors::humans::Human;
+use crate::simulation::actors::reward::{RewardConfig, RewardFunction};
+use crate::simulation::actors::utility::{UtilityBehaviour, UtilityWeights};
+use crate::simulation::map;
+use crate::simulation::snapshot::WorldSnapshot;
+use crate::simulation::trajectory::{self, Trajectory};
+use crate::simulation::world::{Element, World};
+use crate::types::{rng_from_seed, Position, SimRng};
+
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct WorldConfig {
+    pub width : usize,
+    pub height : usize,
+    #[serde(default = "default_cell_size")]
+    pub cell_size : usize,
+    #[serde(default)]
+    pub depletion : f64
+}
+
+fn default_cell_size() -> usize {
+    10
+}
+
+/// Cells from `from` included to `to` excluded.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct Area {
+    pub from : Position,
+    pub to : Position
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct MapConfig {
+    /// Map file, relative to the scenario. Its size replaces the world size.
+    pub file : Option<PathBuf>,
+    #[serde(default)]
+    pub forests : Vec<Area>,
+    #[serde(default)]
+    pub lakes : Vec<Area>
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum BehaviourConfig {
+    /// Trained with the `training` section of the scenario.
+    QLearning {
+        #[serde(default)]
+        online : Option<OnlineLearning>
+    },
+    /// Behaviour tree file relative to the scenario, the survival tree if none.
+    Tree {
+        file : Option<PathBuf>
+    },
+    Utility {
+        #[serde(default)]
+        weights : UtilityWeights
+    },
+    Goap {
+        #[serde(default = "default_urgency")]
+        urgency_threshold : i32,
+        #[serde(default = "default_satiety")]
+        satiety_threshold : i32
+    }
+}
+
+fn default_urgency() -> i32 {
+    60
+}
+
+fn default_satiety() -> i32 {
+    90
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct HumanConfig {
+    pub position : Position,
+    /// Name of an entry of `behaviours`.
+    pub behaviour : String
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TrainingConfig {
+    pub iterations : usize,
+    pub alpha : f64,
+    pub gamma : f64,
+    pub epsilon : f64,
+    pub evaluation_episodes : usize,
+    /// Whether several humans of the same Q-learning behaviour, trained
+    /// together, share one policy or each learn their own.
+    #[serde(default)]
+    pub sharing : PolicySharing,
+    /// Humans trained together learn from their mean reward.
+    #[serde(default)]
+    pub team_reward : bool
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RunConfig {
+    #[serde(default = "default_time_step")]
+    pub time_step_ms : u64,
+    /// Number of simulation steps, unbounded if missing.
+    pub ticks : Option<u64>
+}
+
+fn default_time_step() -> u64 {
+    200
+}
+
+impl Default for RunConfig {
+    fn default() -> Self {
+        RunConfig { time_step_ms : default_time_step(), ticks : None }
+    }
+}
+
+/// Everything an experiment needs : the world, its map, the humans and their
+/// behaviours, how to train them and how long to run.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Scenario {
+    pub world : WorldConfig,
+    #[serde(default)]
+    pub map : MapConfig,
+    pub behaviours : BTreeMap<String, BehaviourConfig>,
+    #[serde(default)]
+    pub humans : Vec<HumanConfig>,
+    pub training : TrainingConfig,
+    #[serde(default)]
+    pub run : RunConfig,
+    /// Reward of every human, the default one if missing.
+    pub reward : Option<RewardConfig>,
+    /// Seed of every random choice of the run, a random one if missing.
+    pub seed : Option<u64>,
+    /// Directory the relative paths of the scenario start from.
+    #[serde(skip)]
+    pub base_dir : PathBuf
+}
+
+/// Trained Q-learning behaviours of a scenario, by name. Policies learnt by
+/// each human on its own are named after `independent_name`.
+pub type LearnedBehaviours = BTreeMap<String, Arc<RwLock<QLBehaviour>>>;
+
+/// Name of the policy the `rank`-th human of the behaviour `name` learnt on
+/// its own.
+pub fn independent_name(name : &str, rank : usize) -> String {
+    format!("{name}#{rank}")
+}
+
+/// Behaviour of the scenario a learned policy belongs to.
+pub fn behaviour_name(name : &str) -> &str {
+    name.split('#').next().unwrap_or(name)
+}
+
+fn invalid_data<E : ToString>(error : E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, error.to_string())
+}
+
+impl Scenario {
+    /// Reads a TOML scenario, or a JSON one if the file ends in `.json`.
+    pub fn load<P : AsRef<Path>>(path : P) -> io::Result<Scenario> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)?;
+        let mut scenario : Scenario = if path.extension().is_some_and(|extension| extension == "json") {
+            serde_json::from_str(&text)?
+        } else {
+            toml::from_str(&text).map_err(invalid_data)?
+        };
+        scenario.base_dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+        scenario.validate()?;
+        Ok(scenario)
+    }
+
+    /// Rejects settings that contradict each other.
+    pub fn validate(&self) -> io::Result<()> {
+        // Shaping only leaves the optimal policy unchanged with the discount
+        // of the learner.
+        if let Some(shaping) = self.reward.as_ref().and_then(|reward| reward.shaping.as_ref()) {
+            if shaping.gamma != self.training.gamma {
+                return Err(invalid_data(format!("shaping gamma {} differs from training gamma {}", shaping.gamma, self.training.gamma)));
+            }
+            for (name, config) in self.behaviours.iter() {
+                if let BehaviourConfig::QLearning { online : Some(online) } = config {
+                    if shaping.gamma != online.gamma {
+                        return Err(invalid_data(format!("shaping gamma {} differs from the online gamma {} of {name}", shaping.gamma, online.gamma)));
+                    }
+                }
+            }
+        }
+
+        // The map file, if any, gives the size of the world.
+        let (width, height) = match &self.map.file {
+            Some(file) => {
+                let cells = map::load(self.resolve(file))?;
+                (cells.width(), cells.height())
+            },
+            None => (self.world.width, self.world.height)
+        };
+        for (index, human) in self.humans.iter().enumerate() {
+            let Position { x, y } = human.position;
+            if x < 0 || y < 0 || x as usize >= width || y as usize >= height {
+                return Err(invalid_data(format!("human {index} ({}) at ({x}, {y}) is outside the {width}x{height} world", human.behaviour)));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn resolve<P : AsRef<Path>>(&self, path : P) -> PathBuf {
+        self.base_dir.join(path)
+    }
+
+    /// New generator seeded with the seed of the scenario.
+    pub fn rng(&self) -> SimRng {
+        rng_from_seed(self.seed)
+    }
+
+    pub fn time_step(&self) -> Duration {
+        Duration::from_millis(self.run.time_step_ms)
+    }
+
+    /// World with its map, without humans.
+    pub fn build_world(&self) -> io::Result<World> {
+        let mut world = match &self.map.file {
+            Some(file) => World::from_cells(map::load(self.resolve(file))?, self.world.cell_size),
+            None => World::new(self.world.width, self.world.height, self.world.cell_size)
+        };
+        {
+            // Regions are rebuilt from the cells, rectangles may overlap.
+            let mut environment = world.environment.write().unwrap();
+            for forest in self.map.forests.iter() {
+                environment.cells.fill_region(forest.from, forest.to, Element::Tree(1.0));
+            }
+            for lake in self.map.lakes.iter() {
+                environment.cells.fill_region(lake.from, lake.to, Element::Water(1.0));
+            }
+            environment.revision += 1;
+            environment.update_regions();
+            environment.depletion = self.world.depletion;
+        }
+        world.rng = self.rng();
+        Ok(world)
+    }
+
+    pub fn reward(&self) -> Arc<dyn RewardFunction> {
+        Arc::new(self.reward.clone().unwrap_or_default())
+    }
+
+    /// Human at `position`, its generator seeded with the seed of the scenario.
+    /// `World::add_human` reseeds it from the generator of the world, itself
+    /// seeded by the scenario, so the humans of a run each draw their own
+    /// numbers and the same ones from one run to the next.
+    pub fn new_human(&self, position : Position, behaviour : SharedBehaviour, world : &World) -> Human {
+        let mut human = Human::new(position.x, position.y, behaviour, world.environment.clone());
+        human.rng = self.rng();
+        human.reward = self.reward();
+        human
+    }
+
+    /// Human living simulated lives in `world` for training or evaluation,
+    /// each life starting from the current cells.
+    fn episode_human(&self, behaviour : SharedBehaviour, world : &World) -> Human {
+        let mut human = self.new_human(Position::new(0, 0), behaviour, world);
+        human.episode_cells = Some(world.environment.read().unwrap().cells.clone());
+        human
+    }
+
+    /// Runs `f` on `world`, then puts back the resources it consumed.
+    fn preserving_cells<T, F : FnOnce() -> T>(world : &World, f : F) -> T {
+        let initial_cells = world.environment.read().unwrap().cells.clone();
+        let result = f();
+        world.environment.write().unwrap().set_cells(initial_cells);
+        result
+    }
+
+    /// Trains every Q-learning behaviour of the scenario in `world`, leaving
+    /// its cells untouched.
+    pub fn train(&self, world : &World) -> LearnedBehaviours {
+        Self::preserving_cells(world, || self.train_behaviours(world))
+    }
+
+    /// Behaviours driven by more than one human of the scenario are trained
+    /// with all of them living together, the others on a lone human.
+    fn train_behaviours(&self, world : &World) -> LearnedBehaviours {
+        let mut learned = LearnedBehaviours::new();
+        for (name, config) in self.behaviours.iter() {
+            if let BehaviourConfig::QLearning { online } = config {
+                println!("Training {name}");
+                let positions : Vec<Position> = self.humans.iter()
+                .filter(|human| human.behaviour == *name)
+                .map(|human| human.position)
+                .collect();
+                let mut trained = if positions.len() > 1 {
+                    self.train_together(&positions, world)
+                } else {
+                    let mut behaviour = QLBehaviour::new();
+                    let mut train_human = self.episode_human(Arc::new(RwLock::new(QLBehaviour::new())), world);
+                    behaviour.train(&mut train_human, self.training.iterations,
+                        self.training.alpha, self.training.gamma, self.training.epsilon);
+                    vec![behaviour]
+                };
+                for behaviour in trained.iter_mut() {
+                    behaviour.set_online(*online);
+                }
+                if trained.len() == 1 {
+                    learned.insert(name.clone(), Arc::new(RwLock::new(trained.remove(0))));
+                } else {
+                    for (rank, behaviour) in trained.into_iter().enumerate() {
+                        learned.insert(independent_name(name, rank), Arc::new(RwLock::new(behaviour)));
+                    }
+                }
+            }
+        }
+        learned
+    }
+
+    /// Trains humans starting at `positions` together in `world`, see
+    /// `QLBehaviour::train_multi_agent`.
+    fn train_together(&self, positions : &[Position], world : &World) -> Vec<QLBehaviour> {
+        let training = MultiAgentTraining {
+            iterations : self.training.iterations,
+            alpha : self.training.alpha,
+            gamma : self.training.gamma,
+            epsilon : self.training.epsilon,
+            sharing : self.training.sharing,
+            team_reward : self.training.team_reward
+        };
+        let mut rng = self.rng();
+        let mut humans : Vec<Human> = positions.iter()
+        .map(|position| {
+            let mut human = self.new_human(*position, Arc::new(RwLock::new(QLBehaviour::new())), world);
+            human.rng = SimRng::seed_from_u64(rng.gen());
+            human
+        })
+        .collect();
+        QLBehaviour::train_multi_agent(&training, &mut humans, &mut rng)
+    }
+
+    /// Writes the policies of `learned` to a single file.
+    pub fn save_learned<P : AsRef<Path>>(learned : &LearnedBehaviours, path : P) -> io::Result<()> {
+        let policies : BTreeMap<&String, Policy> = learned.iter()
+        .map(|(name, behaviour)| (name, behaviour.read().unwrap().policy().clone()))
+        .collect();
+        let writer = BufWriter::new(File::create(path)?);
+        bincode::serialize_into(writer, &policies).map_err(invalid_data)
+    }
+
+    /// Reads policies written by `save_learned`, for the Q-learning
+    /// behaviours of this scenario.
+    pub fn load_learned<P : AsRef<Path>>(&self, path : P) -> io::Result<LearnedBehaviours> {
+        let reader = BufReader::new(File::open(path)?);
+        let policies : BTreeMap<String, Policy> = bincode::deserialize_from(reader).map_err(invalid_data)?;
+        let mut learned = LearnedBehaviours::new();
+        for (name, policy) in policies {
+            let online = match self.behaviours.get(behaviour_name(&name)) {
+                Some(BehaviourConfig::QLearning { online }) => *online,
+                _ => return Err(invalid_data(format!("policy {name} is not a Q-learning behaviour of the scenario")))
+            };
+            let mut behaviour = QLBehaviour::from_policy(policy);
+            behaviour.set_online(online);
+            learned.insert(name, Arc::new(RwLock::new(behaviour)));
+        }
+        Ok(learned)
+    }
+
+    /// Behaviour named `name`. Learned behaviours are shared, the others are
+    /// created anew for each call.
+    pub fn behaviour(&self, name : &str, learned : &LearnedBehaviours) -> io::Result<SharedBehaviour> {
+        if let Some(behaviour) = learned.get(name) {
+            return Ok(behaviour.clone());
+        }
+        let behaviour : SharedBehaviour = match self.behaviours.get(name) {
+            Some(BehaviourConfig::QLearning { .. }) => return Err(invalid_data(format!("behaviour {name} is not trained"))),
+            Some(BehaviourConfig::Tree { file : Some(file) }) => Arc::new(RwLock::new(BTBehaviour::load(self.resolve(file))?)),
+            Some(BehaviourConfig::Tree { file : None }) => Arc::new(RwLock::new(BTBehaviour::survival())),
+            Some(BehaviourConfig::Utility { weights }) => Arc::new(RwLock::new(UtilityBehaviour::new(*weights))),
+            Some(BehaviourConfig::Goap { urgency_threshold, satiety_threshold }) =>
+                Arc::new(RwLock::new(GoapBehaviour::new(Planner::default(), *urgency_threshold, *satiety_threshold))),
+            None => return Err(invalid_data(format!("unknown behaviour {name}")))
+        };
+        Ok(behaviour)
+    }
+
+    /// Names of the behaviours humans can be given : those of the scenario,
+    /// with each policy learnt on its own in place of its behaviour.
+    pub fn behaviour_names(&self, learned : &LearnedBehaviours) -> Vec<String> {
+        let mut names = Vec::new();
+        for name in self.behaviours.keys() {
+            let own : Vec<String> = learned.keys()
+            .filter(|learned_name| *learned_name != name && behaviour_name(learned_name) == name)
+            .cloned()
+            .collect();
+            if own.is_empty() {
+                names.push(name.clone());
+            } else {
+                names.extend(own);
+            }
+        }
+        names
+    }
+
+    /// Behaviour of the human `index` of the scenario, its own policy if it
+    /// learnt one.
+    pub fn human_behaviour(&self, index : usize, learned : &LearnedBehaviours) -> io::Result<SharedBehaviour> {
+        let name = &self.humans[index].behaviour;
+        let rank = self.humans[..index].iter().filter(|human| human.behaviour == *name).count();
+        match learned.get(&independent_name(name, rank)) {
+            Some(behaviour) => Ok(behaviour.clone()),
+            None => self.behaviour(name, learned)
+        }
+    }
+
+    /// Evaluates every behaviour of the scenario on a lone human, each one
+    /// starting from the cells of `world`.
+    pub fn evaluate(&self, world : &World, learned : &LearnedBehaviours) -> io::Result<Vec<(String, EvaluationReport)>> {
+        let mut reports = Vec::new();
+        for name in self.behaviour_names(learned).iter() {
+            let behaviour = self.behaviour(name, learned)?;
+            let mut test_human = self.episode_human(behaviour.clone(), world);
+            println!("Evaluating {name}");
+            let report = Self::preserving_cells(world,
+                || behaviour.read().unwrap().evaluate(&mut test_human, self.training.evaluation_episodes));
+            reports.push((name.clone(), report));
+        }
+        Ok(reports)
+    }
+
+    /// Paths of the first `episodes` evaluation lives of every behaviour.
+    pub fn trajectories(&self, world : &World, learned : &LearnedBehaviours, episodes : usize) -> io::Result<Vec<Trajectory>> {
+        let mut trajectories = Vec::new();
+        for name in self.behaviour_names(learned).iter() {
+            let behaviour = self.behaviour(name, learned)?;
+            let mut test_human = self.episode_human(behaviour.clone(), world);
+            trajectories.extend(Self::preserving_cells(world,
+                || trajectory::trace(name, &*behaviour.read().unwrap(), &mut test_human, episodes)));
+        }
+        Ok(trajectories)
+    }
+
+    /// Adds the humans of the scenario to `world`.
+    pub fn populate(&self, world : &mut World, learned : &LearnedBehaviours) -> io::Result<()> {
+        for (index, config) in self.humans.iter().enumerate() {
+            let human = self.new_human(config.position, self.human_behaviour(index, learned)?, world);
+            world.add_human(human);
+        }
+        Ok(())
+    }
+
+    /// Resumes a snapshot of a world populated by this scenario.
+    pub fn restore(&self, snapshot : &WorldSnapshot, learned : &LearnedBehaviours) -> io::Result<World> {
+        // Same behaviours, in the same order, as `World::behaviours` after `populate`.
+        let mut behaviours : Vec<SharedBehaviour> = Vec::new();
+        for index in 0..self.humans.len() {
+            let behaviour = self.human_behaviour(index, learned)?;
+            if !behaviours.iter().any(|known| Arc::ptr_eq(known, &behaviour)) {
+                behaviours.push(behaviour);
+            }
+        }
+        snapshot.restore(&behaviours, self.reward())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SMALL : &str = r#"
+        seed = 3
+
+        [world]
+        width = 4
+        height = 3
+
+        [[map.forests]]
+        from = { x = 0, y = 0 }
+        to = { x = 1, y = 1 }
+
+        [[map.lakes]]
+        from = { x = 3, y = 2 }
+        to = { x = 4, y = 3 }
+
+        [behaviours.learned]
+        type = "QLearning"
+
+        [[humans]]
+        position = { x = 1, y = 1 }
+        behaviour = "learned"
+
+        [[humans]]
+        position = { x = 2, y = 1 }
+        behaviour = "learned"
+
+        [training]
+        iterations = 3
+        alpha = 0.2
+        gamma = 0.6
+        epsilon = 0.8
+        evaluation_episodes = 2
+    "#;
+
+    fn small(extra : &str) -> Scenario {
+        toml::from_str(&format!("{SMALL}{extra}")).unwrap()
+    }
+
+    #[test]
+    fn shaping_uses_the_discount_of_the_learners() {
+        let shaping = "\n[reward]\ncomponents = []\n[reward.shaping]\npotentials = [[\"Needs\", 1.0]]\ngamma = ";
+        assert!(small(&format!("{shaping}0.6\n")).validate().is_ok());
+        assert!(small(&format!("{shaping}0.9\n")).validate().is_err());
+
+        let mut online = small(&format!("{shaping}0.6\n"));
+        online.behaviours.insert("learned".to_string(),
+            BehaviourConfig::QLearning { online : Some(OnlineLearning { alpha : 0.1, gamma : 0.9, epsilon : 0.05 }) });
+        assert!(online.validate().is_err());
+    }
+
+    #[test]
+    fn humans_stay_in_the_world() {
+        assert!(small("").validate().is_ok());
+        let mut outside = small("");
+        outside.humans[1].position = Position::new(4, 0);
+        let error = outside.validate().unwrap_err().to_string();
+        assert!(error.contains("human 1 (learned) at (4, 0)"), "{error}");
+        outside.humans[1].position = Position::new(0, -1);
+        assert!(outside.validate().is_err());
+    }
+
+    #[test]
+    fn seeded_runs_give_humans_the_same_generators() {
+        let scenario = small("");
+        let learned = scenario.train(&scenario.build_world().unwrap());
+        let generators = || {
+            let mut world = scenario.build_world().unwrap();
+            scenario.populate(&mut world, &learned).unwrap();
+            world.humans.iter_mut().map(|human| human.rng.gen::<u64>()).collect::<Vec<u64>>()
+        };
+        let first = generators();
+        assert_eq!(first, generators());
+        assert_ne!(first[0], first[1]);
+    }
+
+    #[test]
+    fn humans_sharing_a_behaviour_train_together() {
+        let scenario = small("");
+        let learned = scenario.train(&scenario.build_world().unwrap());
+        assert_eq!(learned.keys().collect::<Vec<_>>(), vec!["learned"]);
+
+        let mut world = scenario.build_world().unwrap();
+        scenario.populate(&mut world, &learned).unwrap();
+        assert!(Arc::ptr_eq(&world.humans[0].behaviour, &world.humans[1].behaviour));
+    }
+
+    #[test]
+    fn independent_humans_get_their_own_policy() {
+        let scenario = small("sharing = \"Independent\"\nteam_reward = true\n");
+        let learned = scenario.train(&scenario.build_world().unwrap());
+        assert_eq!(learned.keys().collect::<Vec<_>>(), vec!["learned#0", "learned#1"]);
+        assert_eq!(scenario.behaviour_names(&learned), vec!["learned#0", "learned#1"]);
+
+        let mut world = scenario.build_world().unwrap();
+        scenario.populate(&mut world, &learned).unwrap();
+        let own : SharedBehaviour = learned["learned#1"].clone();
+        assert!(Arc::ptr_eq(&world.humans[1].behaviour, &own));
+        assert_eq!(scenario.evaluate(&world, &learned).unwrap().len(), 2);
+    }
+}
@@ -1,41 +1,214 @@
 
-use crate::learning::qlearning::{Agent, Policy, State};
+use crate::learning::evaluation::{self, EvaluationReport};
+use crate::learning::multiagent::MultiAgentTraining;
+use crate::learning::qlearning::{Agent, Policy, State, Transition};
 use crate::simulation::actors::humans::Human;
-use crate::simulation::world::{Element, Environment};
-use crate::types::Position;
+use crate::simulation::events::EventKind;
+use crate::simulation::world::{Element, Environment, Resource};
+use crate::types::{Grid, Position, SimRng};
 
-use rand::Rng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
 use std::cmp::max;
+use std::io;
+use std::sync::{Arc, Mutex, RwLock};
+
+// Action indices understood by `Agent::do_action`.
+pub const MOVE_RIGHT : usize = 0;
+pub const MOVE_LEFT : usize = 1;
+pub const MOVE_DOWN : usize = 2;
+pub const MOVE_UP : usize = 3;
+pub const DRINK : usize = 4;
+pub const EAT : usize = 5;
+pub const WAIT : usize = 6;
+pub const NB_ACTIONS : usize = 7;
+pub const ACTION_NAMES : [&str; NB_ACTIONS] = ["right", "left", "down", "up", "drink", "eat", "wait"];
+
+/// Age at which simulated lives are cut short.
+pub const MAX_AGE : u32 = 10000;
+
+/// Behaviour handle shared between the humans driven by it.
+pub type SharedBehaviour = Arc<RwLock<dyn Behaviour>>;
+
+/// Hyperparameters of the updates made while the live simulation runs.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct OnlineLearning {
+    pub alpha : f64,
+    pub gamma : f64,
+    /// Exploration rate of the live humans.
+    pub epsilon : f64
+}
+
+/// Greedy decisions of a Q-learning policy on every cell of a world, for a
+/// human with fixed need levels.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PolicyMap {
+    pub thirst_level : usize,
+    pub hunger_level : usize,
+    pub actions : Grid<usize>,
+    pub values : Grid<f64>,
+    pub visits : Grid<u32>
+}
+
+/// Action a learning human took, turned into a transition once the next tick
+/// has decayed its needs, as in `Agent::simulate_action`.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PendingStep {
+    /// Key of the state the action was taken in.
+    pub state : usize,
+    pub action : usize,
+    pub action_reward : f64,
+    /// Potential of that state, for the shaping term.
+    pub potential : f64
+}
 
 pub struct QLBehaviour {
-    policy : Policy
+    policy : Policy,
+    online : Option<OnlineLearning>,
+    // Transitions of the live humans waiting for the next `learn`.
+    pending : Mutex<Vec<Transition>>
 }
 
 impl QLBehaviour {
     pub fn new() -> QLBehaviour {
         QLBehaviour {
-            policy : Policy::new()
+            policy : Policy::new(),
+            online : None,
+            pending : Mutex::new(Vec::new())
+        }
+    }
+
+    /// Creates an untrained policy sized for the world of `train_agent`.
+    pub fn init(&mut self, train_agent : &mut Human) {
+        self.policy.init(nb_states(train_agent), NB_ACTIONS, &mut train_agent.rng);
+    }
+
+    pub fn from_policy(policy : Policy) -> QLBehaviour {
+        QLBehaviour { policy, ..QLBehaviour::new() }
+    }
+
+    pub fn policy(&self) -> &Policy {
+        &self.policy
+    }
+
+    /// What the policy would do on each cell of `environment` with the given
+    /// need levels, see `need_level`.
+    pub fn policy_map(&self, environment : &Environment, thirst_level : usize, hunger_level : usize) -> PolicyMap {
+        let cells = &environment.cells;
+        let states = Grid::from_fn(cells.width(), cells.height(), |position| encode_at(environment, position, thirst_level, hunger_level));
+        PolicyMap {
+            thirst_level,
+            hunger_level,
+            actions : states.map(|state| self.policy.predict_action(state)),
+            values : states.map(|state| self.policy.max_value(state)),
+            visits : states.map(|state| self.policy.visits(state))
         }
     }
 
-    fn init(&mut self, train_agent : &mut Human) {
-        self.policy.init(nb_states(&train_agent), 7);
+    /// Enables or disables learning from the humans of the live simulation.
+    /// The policy has to be trained or initialised beforehand.
+    pub fn set_online(&mut self, online : Option<OnlineLearning>) {
+        self.online = online;
+    }
+
+    pub fn online(&self) -> Option<OnlineLearning> {
+        self.online
     }
 
     pub fn train(&mut self, train_agent: &mut Human, iterations: usize, alpha: f64, gamma: f64, epsilon: f64) {
         self.init(train_agent);
-        self.policy.train(train_agent, iterations, alpha, gamma, epsilon)
+        let mut rng = SimRng::seed_from_u64(train_agent.rng.gen());
+        self.policy.train(train_agent, iterations, alpha, gamma, epsilon, &mut rng)
+    }
+
+    /// Trains humans living together in their shared environment, which is
+    /// restored before every episode. Returns one behaviour shared by every
+    /// human, or one per human, depending on `training.sharing`.
+    pub fn train_multi_agent(training : &MultiAgentTraining, humans : &mut [Human], rng : &mut SimRng) -> Vec<QLBehaviour> {
+        assert!(!humans.is_empty());
+        let mut policies : Vec<Policy> = (0..training.nb_policies(humans.len()))
+        .map(|_| {
+            let mut policy = Policy::new();
+            policy.init(nb_states(&humans[0]), NB_ACTIONS, rng);
+            policy
+        })
+        .collect();
+
+        let environment = humans[0].environment.clone();
+        let initial_cells = environment.read().unwrap().cells.clone();
+        let reset_world = || environment.write().unwrap().set_cells(initial_cells.clone());
+        training.train(&mut policies, humans, reset_world, rng);
+        reset_world();
+
+        policies.into_iter()
+        .map(QLBehaviour::from_policy)
+        .collect()
     }
+}
+
 
-    pub fn evaluate(&self, test_agent : &mut Human, iterations: usize) {
-        self.policy.evaluate(test_agent, iterations)
+impl Default for QLBehaviour {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
+/// How a behaviour sees a human, for display.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Inspection {
+    /// Key of the encoded state of the human.
+    pub state : usize,
+    /// Value of each action in that state.
+    pub q_values : [f64; NB_ACTIONS]
+}
 
-pub trait Behaviour {
+pub trait Behaviour : Send + Sync {
     fn predict_action(&self, human : &Human) -> usize;    
     fn step(&self, human : &mut Human);
+
+    /// Applies what was gathered during the last steps. Called by the world
+    /// once per tick, the only time it takes the write lock of the behaviour.
+    fn learn(&mut self) {}
+
+    /// Forgets any progress kept between steps, called when a new life starts.
+    fn reset(&self) {}
+
+    /// Serialized state the behaviour changes while the world runs, kept in
+    /// world snapshots. `None` if there is nothing to keep.
+    fn save_state(&self) -> Option<Vec<u8>> {
+        None
+    }
+
+    /// Restores a state written by `save_state`.
+    fn restore_state(&mut self, _state : &[u8]) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// What the behaviour makes of `human`, `None` if it keeps no values.
+    fn inspect(&self, _human : &Human) -> Option<Inspection> {
+        None
+    }
+
+    /// Runs `iterations` simulated lives of `test_agent` driven by this behaviour.
+    fn evaluate(&self, test_agent : &mut Human, iterations : usize) -> EvaluationReport {
+        evaluation::evaluate(test_agent, iterations, NB_ACTIONS, |human, _, step| {
+            if step == 0 {
+                self.reset();
+            }
+            self.predict_action(human)
+        })
+    }
+}
+
+/// Action index of the single step that brings `from` closer to `to`,
+/// moving along the axis with the largest gap first.
+pub fn move_towards(from : Position, to : Position) -> usize {
+    let direction = to - from;
+    if direction.x == 0 && direction.y == 0 {
+        WAIT
+    } else if direction.x.abs() >= direction.y.abs() {
+        if direction.x > 0 { MOVE_RIGHT } else { MOVE_LEFT }
+    } else if direction.y > 0 { MOVE_DOWN } else { MOVE_UP }
 }
 
 impl Behaviour for QLBehaviour {
@@ -45,26 +218,96 @@ impl Behaviour for QLBehaviour {
     }
     
     fn step(&self, human : &mut Human) {
-        human.do_action(self.predict_action(human));
+        // The action of the last tick is learnt from now that the needs have
+        // decayed, the human may have died of it.
+        if let Some(step) = human.pending_step.take() {
+            human.last_reward = human.reward.step_reward(human, step.action_reward, step.potential);
+            let transition = Transition {
+                state : State { key : step.state },
+                action : step.action,
+                reward : human.last_reward.total(),
+                next_state : encode(human),
+                done : !human.alive
+            };
+            self.pending.lock().unwrap().push(transition);
+        }
+        if !human.alive {
+            return;
+        }
+
+        let online = match self.online {
+            Some(online) => online,
+            None => {
+                human.do_action(self.predict_action(human));
+                return;
+            }
+        };
+
+        let state = encode(human);
+        let action = if human.rng.gen_range(0.0, 1.0) < online.epsilon {
+            human.rng.gen_range(0, NB_ACTIONS)
+        } else {
+            self.policy.predict_action(&state)
+        };
+        let potential = human.reward.potential(human);
+        let action_reward = human.do_action(action);
+        human.pending_step = Some(PendingStep { state : state.key, action, action_reward, potential });
+    }
+
+    /// The policy, when it changes with online learning.
+    fn save_state(&self) -> Option<Vec<u8>> {
+        self.online.map(|_| bincode::serialize(&self.policy).unwrap())
+    }
+
+    fn restore_state(&mut self, state : &[u8]) -> io::Result<()> {
+        self.policy = bincode::deserialize(state).map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+        Ok(())
+    }
+
+    fn inspect(&self, human : &Human) -> Option<Inspection> {
+        let state = encode(human);
+        let mut q_values = [0.0; NB_ACTIONS];
+        for (action, value) in q_values.iter_mut().enumerate() {
+            *value = self.policy.get_value(&state, action);
+        }
+        Some(Inspection { state : state.key, q_values })
+    }
+
+    fn learn(&mut self) {
+        if let Some(online) = self.online {
+            for transition in self.pending.get_mut().unwrap().drain(..) {
+                self.policy.update(&transition, online.alpha, online.gamma);
+            }
+        }
     }
 }
 
 impl Agent for Human{
     fn reset(&mut self) -> State {
-        let mut rng = rand::thread_rng();
-        self.position = Position{x : rng.gen_range(0, self.environment.read().unwrap().world_limits.0 as i32), 
-                                 y : rng.gen_range(0, self.environment.read().unwrap().world_limits.1 as i32)};
+        let (width, height) = {
+            let mut environment = self.environment.write().unwrap();
+            // Without depletion the cells never change.
+            if let (Some(cells), true) = (&self.episode_cells, environment.depletion > 0.0) {
+                environment.set_cells(cells.clone());
+            }
+            (environment.cells.width(), environment.cells.height())
+        };
+        self.position = Position{x : self.rng.gen_range(0, width as i32), 
+                                 y : self.rng.gen_range(0, height as i32)};
         self.age = 0;
         self.hunger.value = 100;
         self.thirst.value = 100;
         self.energy.value = 100;
         self.money.value = 0;
         self.alive = true;
+        self.pending_step = None;
 
-        encode(&self)
+        encode(self)
     }
 
     fn simulation_step_time(&mut self) {
+        // Nobody listens while training, only the last step is kept.
+        self.events.clear();
         {
             self.hunger.value = max(self.hunger.value - 1, 0);
             if self.hunger.value <= 0 {
@@ -88,46 +331,19 @@ impl Agent for Human{
     }
 
     fn compute_reward(&self) -> f64{
-        if !self.alive {
-            return -1000.0;
-        }
-
-        let hunger_reward = if self.hunger.value > 80 { 1.0 }
-        else if self.hunger.value > 50 { 0.0 }
-        else if self.hunger.value > 20 { -30.0 } 
-        else { -100.0 };
-
-        let thirst_reward = if self.thirst.value > 80 { 1.0 }
-        else if self.thirst.value > 50 { 0.0 }
-        else if self.thirst.value > 20 { -30.0 } 
-        else { -100.0 };
-
-        let env = self.environment.read().unwrap();
-        let resource_reward = match env.get_element(self.position.x as usize, self.position.y as usize) {
-            Element::Water(_) => 1.0,
-            Element::Tree(_) => 1.0,
-            _ => 0.0
-        };
-
-        let age_reward = (self.age as f64 - 100.0).max(0.0) * 10000.0;
-
-        hunger_reward + thirst_reward +resource_reward + age_reward
+        self.reward.reward(self).total()
     }
 
     fn simulate_action(&mut self, action : usize) -> (State, f64, bool) {
-        let mut reward = 0.0;
-        match action {
-            0 => reward = Move::execute(self, Position::new(1, 0)),
-            1 => reward = Move::execute(self, Position::new(-1, 0)),
-            2 => reward = Move::execute(self, Position::new(0, 1)),
-            3 => reward = Move::execute(self, Position::new(0, -1)),
-            4 => reward = Drink::execute(self, 30),
-            5 => reward = Eat::execute(self, 30),
-            _ => ()
-            
-        }
+        let potential = self.reward.potential(self);
+        let reward = self.do_action(action);
         self.simulation_step_time();        
-        (encode(&self), reward + self.compute_reward(), !self.alive || self.age > 10000)
+        self.last_reward = self.reward.step_reward(self, reward, potential);
+        (encode(self), self.last_reward.total(), !self.alive)
+    }
+
+    fn truncated(&self) -> bool {
+        self.age > MAX_AGE
     }
 
     fn choose_action(&self) -> usize {
@@ -135,20 +351,22 @@ impl Agent for Human{
         behaviour.predict_action(self)
     }
 
-    fn do_action(&mut self, action : usize) {
+    fn do_action(&mut self, action : usize) -> f64 {
+        self.last_action = Some(action);
         match action {
-            0 => Move::execute(self, Position::new(1, 0)),
-            1 => Move::execute(self, Position::new(-1, 0)),
-            2 => Move::execute(self, Position::new(0, 1)),
-            3 => Move::execute(self, Position::new(0, -1)),
-            4 => Drink::execute(self, 30),
-            5 => Eat::execute(self, 30),
+            MOVE_RIGHT => Move::execute(self, Position::new(1, 0)),
+            MOVE_LEFT => Move::execute(self, Position::new(-1, 0)),
+            MOVE_DOWN => Move::execute(self, Position::new(0, 1)),
+            MOVE_UP => Move::execute(self, Position::new(0, -1)),
+            DRINK => Drink::execute(self, 30),
+            EAT => Eat::execute(self, 30),
             _ => 0.0
-        };
+        }
     }
 
     fn step(&mut self) {
-        self.do_action(self.choose_action());
+        let behaviour = self.behaviour.clone();
+        behaviour.read().unwrap().step(self);
     }
 }
 
@@ -163,9 +381,14 @@ impl Action for Drink {
     type Item = i32;
     fn execute(human: &mut Human, value : Self::Item) -> f64{
         let previous_thirst = human.thirst.value;
-        if let Element::Water(_) = human.environment.read().unwrap()
-            .get_element(human.position.x as usize, human.position.y as usize) {
+        let mut environment = human.environment.write().unwrap();
+        if let Element::Water(_) = environment.cells[human.position] {
+                let depleted = environment.consume(human.position);
                 human.thirst.value = 100.min(human.thirst.value + value);
+                human.events.push(EventKind::Consumed { resource : Resource::Water, amount : human.thirst.value - previous_thirst });
+                if depleted {
+                    human.events.push(EventKind::ResourceDepleted { resource : Resource::Water, position : human.position });
+                }
                 return (human.thirst.value - previous_thirst) as f64 * 10.0;
             }
         -1.0
@@ -177,10 +400,14 @@ impl Action for Eat {
     type Item = i32;
     fn execute(human: &mut Human, value : Self::Item) -> f64{
         let previous_hunger = human.hunger.value;
-        
-        if let Element::Tree(_) = human.environment.read().unwrap()
-                                .get_element(human.position.x as usize, human.position.y as usize) {
+        let mut environment = human.environment.write().unwrap();
+        if let Element::Tree(_) = environment.cells[human.position] {
+            let depleted = environment.consume(human.position);
             human.hunger.value = 100.min(human.hunger.value + value);
+            human.events.push(EventKind::Consumed { resource : Resource::Food, amount : human.hunger.value - previous_hunger });
+            if depleted {
+                human.events.push(EventKind::ResourceDepleted { resource : Resource::Food, position : human.position });
+            }
             return (human.hunger.value - previous_hunger) as f64* 10.0;
         }
         -1.0
@@ -192,86 +419,114 @@ pub struct Move;
 impl Action for Move {
     type Item = Position;
     fn execute(human: &mut Human, value : Self::Item) -> f64 {
-        human.position = human.position + value;
-        let world_limits = human.environment.read().unwrap().world_limits;
+        let from = human.position;
+        let to = human.position + value;
+        human.position = human.environment.read().unwrap().cells.clamp(to);
 
-        if human.position.x < 0 || human.position.x >= world_limits.0 as i32 {
-            human.position.x = human.position.x.clamp(0, world_limits.0 as i32 - 1);
+        if human.position != to {
+            human.events.push(EventKind::BlockedByWall { position : human.position });
             return -1.0;
         }
-        if human.position.y < 0 || human.position.y >= world_limits.1 as i32 {
-            human.position.y = human.position.y.clamp(0, world_limits.1 as i32 - 1);
-            return -1.0
-        }
 
+        human.events.push(EventKind::Moved { from, to : human.position });
         0.0
     }
 } 
 
 
-fn encode(human: &Human) -> State {
-    let env = human.environment.read().unwrap();
-    let thirst_state = match human.thirst.value {
-        v if v > 80 => 0,
-        v if v > 50 => 1,
-        v if v > 20 => 2,
-        _ => 3,
-    };
+/// Number of levels a need is split into by the state encoding.
+pub const NB_NEED_LEVELS : usize = 4;
 
-    let hunger_state = match human.hunger.value {
+/// Level of a need value in the state encoding, from 0 above 80 to 3 at 20
+/// and below.
+pub fn need_level(value : i32) -> usize {
+    match value {
         v if v > 80 => 0,
         v if v > 50 => 1,
         v if v > 20 => 2,
         _ => 3,
-    };
+    }
+}
 
-    let closest_lake = env.closest_lake(human);
-    let lake_direction = *closest_lake - human.position;
-    let lake_direction_state = 
-        if lake_direction.x.abs() > lake_direction.y.abs() {
-            if lake_direction.x >= 0 { 0 } 
-            else { 1 }
-        } else {
-            if lake_direction.y >= 0 { 2 } 
-            else { 3 }
-        };
+fn encode(human: &Human) -> State {
+    let env = human.environment.read().unwrap();
+    encode_at(&env, human.position, need_level(human.thirst.value), need_level(human.hunger.value))
+}
 
-    let closest_forest = env.closest_forest(human);
-    let forest_direction = *closest_forest - human.position;
-    let forest_direction_state = 
-        if forest_direction.x.abs() > forest_direction.y.abs() {
-            if forest_direction.x >= 0 { 0 } 
-            else { 1 }
-        } else {
-            if forest_direction.y >= 0 { 2 } 
-            else { 3 }
-        };
+/// Direction of `target` seen from `position` : 0 right, 1 left, 2 down or
+/// 3 up. A missing target, when the world has no such resource, counts as
+/// right so that such worlds can still be encoded.
+fn direction_state(target : Option<&Position>, position : Position) -> usize {
+    let direction = match target {
+        Some(target) => *target - position,
+        None => return 0
+    };
+    if direction.x.abs() > direction.y.abs() {
+        if direction.x >= 0 { 0 } 
+        else { 1 }
+    } else if direction.y >= 0 { 2 } 
+    else { 3 }
+}
+
+/// State of a human standing at `position` with the given need levels.
+fn encode_at(env : &Environment, position : Position, thirst_state : usize, hunger_state : usize) -> State {
+    let lake_direction_state = direction_state(env.closest_lake_to(position), position);
+    let forest_direction_state = direction_state(env.closest_forest_to(position), position);
 
-    let current_element = match env.get_element(human.position.x as usize, human.position.y as usize) {
+    let current_element = match env.cells[position] {
         Element::Water(_) => 0,
         Element::Tree(_) => 1,
         _ => 2,
     };
 
     // Calculate the key using the encoded states
-    let key = (((((human.position.x as usize * env.world_limits.1 + human.position.y as usize) * 4
-        + thirst_state as usize) * 4
-        + hunger_state as usize) * 4
-        + lake_direction_state as usize) * 4
-        + forest_direction_state as usize) * 3
-        + current_element as usize;
-
-    State { key: key as usize }
+    let key = ((((env.cells.index(position).unwrap() * 4
+        + thirst_state) * 4
+        + hunger_state) * 4
+        + lake_direction_state) * 4
+        + forest_direction_state) * 3
+        + current_element;
+
+    State { key }
 }
 
 
 fn nb_states(human : &Human) -> usize {
     let env = human.environment.read().unwrap();
-    env.world_limits.0      // World Height
-    * env.world_limits.1    // World Width
+    env.cells.width()       // World Width
+    * env.cells.height()    // World Height
     * 4                     // Thirst States
     * 4                     // Hunger States    
     * 4                     // Closeness to Forest States
     * 4                     // Closeness to Lake States
     * 3                     // Current Element
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::world::World;
+
+    #[test]
+    fn every_life_starts_with_full_cells() {
+        let world = World::from_cells(Grid::new(1, 1, Element::Water(1.0)), 1);
+        world.environment.write().unwrap().depletion = 0.5;
+        let behaviour : SharedBehaviour = Arc::new(RwLock::new(QLBehaviour::new()));
+        let mut human = Human::new(0, 0, behaviour, world.environment.clone());
+        human.episode_cells = Some(world.environment.read().unwrap().cells.clone());
+
+        let mut lives = 0;
+        evaluation::evaluate(&mut human, 3, NB_ACTIONS, |human, _, step| {
+            let environment = human.environment.read().unwrap();
+            if step == 0 {
+                lives += 1;
+                assert_eq!(environment.cells[Position::new(0, 0)], Element::Water(1.0));
+                assert_eq!(environment.lakes, vec![Position::new(0, 0)]);
+            } else if step > 2 {
+                // Drunk dry during the life.
+                assert_eq!(environment.cells[Position::new(0, 0)], Element::None);
+            }
+            DRINK
+        });
+        assert_eq!(lives, 3);
+    }
+}
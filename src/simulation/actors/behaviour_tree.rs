@@ -0,0 +1,260 @@
+use crate::learning::qlearning::{Agent, Transition};
+use crate::simulation::actors::behaviour::{move_towards, Behaviour, DRINK, EAT, WAIT};
+use crate::simulation::actors::humans::Human;
+use crate::simulation::world::{Element, Resource};
+
+use ai_behavior::{ActionArgs, Behavior, Failure, Running, Select, Sequence, State, Status, Success, While, WaitForever, RUNNING};
+use input::{Event, Loop, UpdateArgs};
+use serde::{Deserialize, Serialize};
+
+use std::fs::File;
+use std::io::{self, BufReader};
+use std::path::Path;
+use std::sync::Mutex;
+
+/// Tests on the human driving the tree.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Condition {
+    ThirstBelow(i32),
+    HungerBelow(i32),
+    EnergyBelow(i32),
+    On(Resource)
+}
+
+impl Condition {
+    pub fn holds(&self, human : &Human) -> bool {
+        match self {
+            Condition::ThirstBelow(value) => human.thirst.value < *value,
+            Condition::HungerBelow(value) => human.hunger.value < *value,
+            Condition::EnergyBelow(value) => human.energy.value < *value,
+            Condition::On(resource) => on_resource(human, *resource)
+        }
+    }
+}
+
+/// Leaves of a behaviour tree.
+///
+/// Every tree tick is one simulation step of `dt = 1.0`. Conditions are
+/// instantaneous, while `GoTo`, `Drink`, `Eat` and `Idle` spend the whole step,
+/// so a tick never produces more than one action.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum TreeAction {
+    /// Succeeds if the condition holds, fails otherwise.
+    Check(Condition),
+    /// Keeps running while the condition holds, succeeds once it stops.
+    /// Meant as the condition of a `While` node.
+    Holds(Condition),
+    /// Walks to the closest cell holding the resource.
+    GoTo(Resource),
+    Drink,
+    Eat,
+    Idle
+}
+
+pub type Tree = Behavior<TreeAction>;
+
+/// Behaviour driven by a scripted behaviour tree.
+///
+/// The tree keeps track of its progress between steps, so every human needs
+/// its own `BTBehaviour` instance.
+pub struct BTBehaviour {
+    tree : Tree,
+    state : Mutex<State<TreeAction, ()>>
+}
+
+impl BTBehaviour {
+    pub fn new(tree : Tree) -> BTBehaviour {
+        BTBehaviour {
+            state : Mutex::new(State::new(tree.clone())),
+            tree
+        }
+    }
+
+    /// Reads a tree serialized as JSON.
+    pub fn load<P : AsRef<Path>>(path : P) -> io::Result<BTBehaviour> {
+        let reader = BufReader::new(File::open(path)?);
+        let tree = serde_json::from_reader(reader)?;
+        Ok(BTBehaviour::new(tree))
+    }
+
+    /// Expert tree : drink when thirsty, eat when hungry, idle otherwise.
+    pub fn survival() -> BTBehaviour {
+        let refill = |need : fn(i32) -> Condition, resource : Resource, consume : TreeAction| Sequence(vec![
+            Behavior::Action(TreeAction::Check(need(50))),
+            Behavior::Action(TreeAction::GoTo(resource)),
+            While(Box::new(Behavior::Action(TreeAction::Holds(need(95)))), vec![
+                Behavior::Action(consume)
+            ])
+        ]);
+
+        BTBehaviour::new(While(Box::new(WaitForever), vec![
+            Select(vec![
+                refill(Condition::ThirstBelow, Resource::Water, TreeAction::Drink),
+                refill(Condition::HungerBelow, Resource::Food, TreeAction::Eat),
+                Behavior::Action(TreeAction::Idle)
+            ])
+        ]))
+    }
+
+    pub fn tree(&self) -> &Tree {
+        &self.tree
+    }
+
+    /// Lets the tree play `episodes` lives of `human` and records every step,
+    /// to be used as expert demonstrations.
+    pub fn demonstrate(&self, human : &mut Human, episodes : usize) -> Vec<Transition> {
+        let mut transitions = Vec::new();
+        for _ in 0..episodes {
+            self.reset();
+            let mut state = human.reset();
+            let mut done = false;
+            while !done {
+                let action = self.predict_action(human);
+                let (next_state, reward, dead) = human.simulate_action(action);
+                transitions.push(Transition { state, action, reward, next_state, done : dead });
+                state = next_state;
+                done = dead || human.truncated();
+            }
+        }
+        transitions
+    }
+}
+
+impl Behaviour for BTBehaviour {
+    /// Ticks the tree once, which advances its state.
+    fn predict_action(&self, human : &Human) -> usize {
+        let mut state = self.state.lock().unwrap();
+        let event = Event::Loop(Loop::Update(UpdateArgs { dt : 1.0 }));
+        let mut action = None;
+        let (status, _) = state.event(&event, &mut |args : ActionArgs<Event, TreeAction, ()>| {
+            tick(human, args.action, args.dt, &mut action)
+        });
+        if status != Running {
+            *state = State::new(self.tree.clone());
+        }
+        action.unwrap_or(WAIT)
+    }
+
+    fn step(&self, human : &mut Human) {
+        human.do_action(self.predict_action(human));
+    }
+
+    /// Restarts the tree from its root.
+    fn reset(&self) {
+        *self.state.lock().unwrap() = State::new(self.tree.clone());
+    }
+
+    /// Progress of the tree.
+    fn save_state(&self) -> Option<Vec<u8>> {
+        Some(bincode::serialize(&*self.state.lock().unwrap()).unwrap())
+    }
+
+    fn restore_state(&mut self, state : &[u8]) -> io::Result<()> {
+        let state = bincode::deserialize(state).map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+        *self.state.get_mut().unwrap() = state;
+        Ok(())
+    }
+}
+
+fn tick(human : &Human, leaf : &TreeAction, dt : f64, action : &mut Option<usize>) -> (Status, f64) {
+    // Nothing left of this step, the leaf will run on the next one.
+    if dt <= 0.0 || action.is_some() {
+        return RUNNING;
+    }
+
+    let chosen = match leaf {
+        TreeAction::Check(condition) => return (if condition.holds(human) { Success } else { Failure }, dt),
+        TreeAction::Holds(condition) => return if condition.holds(human) { RUNNING } else { (Success, dt) },
+        TreeAction::GoTo(resource) => match human.find_closest(resource.element()) {
+            None => return (Failure, dt),
+            Some(target) if target == human.position => return (Success, dt),
+            Some(target) => {
+                *action = Some(move_towards(human.position, target));
+                return RUNNING;
+            }
+        },
+        TreeAction::Drink => if on_resource(human, Resource::Water) { DRINK } else { return (Failure, dt) },
+        TreeAction::Eat => if on_resource(human, Resource::Food) { EAT } else { return (Failure, dt) },
+        TreeAction::Idle => WAIT
+    };
+
+    *action = Some(chosen);
+    (Success, 0.0)
+}
+
+fn on_resource(human : &Human, resource : Resource) -> bool {
+    let environment = human.environment.read().unwrap();
+    let element = environment.cells[human.position];
+    matches!((resource, element), (Resource::Water, Element::Water(_)) | (Resource::Food, Element::Tree(_)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::actors::behaviour::{SharedBehaviour, MOVE_LEFT};
+    use crate::simulation::world::World;
+    use crate::types::{Grid, Position};
+    use std::sync::{Arc, RwLock};
+
+    /// Thirsty human two cells right of the only water, driven by the
+    /// survival tree of the assets.
+    fn thirsty_world() -> World {
+        let mut cells = Grid::new(5, 1, Element::Grass(1.0));
+        cells[Position::new(0, 0)] = Element::Water(1.0);
+        let mut world = World::from_cells(cells, 1);
+        let tree = BTBehaviour::load(Path::new(env!("CARGO_MANIFEST_DIR")).join("assets/trees/survival.json")).unwrap();
+        assert!(tree.tree() == BTBehaviour::survival().tree());
+        let behaviour : SharedBehaviour = Arc::new(RwLock::new(tree));
+        let mut human = Human::new(2, 0, behaviour, world.environment.clone());
+        human.thirst.value = 45;
+        world.add_human(human);
+        world
+    }
+
+    fn run(world : &mut World, ticks : usize) -> Vec<(Position, i32, Option<usize>)> {
+        (0..ticks).map(|_| {
+            world.step_time();
+            let human = &world.humans[0];
+            (human.position, human.thirst.value, human.last_action)
+        })
+        .collect()
+    }
+
+    #[test]
+    fn a_tick_produces_one_action() {
+        let mut world = thirsty_world();
+        let mut position = world.humans[0].position;
+        for (next, _, action) in run(&mut world, 20) {
+            assert!(action.is_some());
+            // A tick that moved did nothing else, and never moved twice.
+            assert!(next.manhattan_dist(&position) <= 1);
+            if next != position {
+                assert_eq!(action, Some(MOVE_LEFT));
+            }
+            position = next;
+        }
+    }
+
+    #[test]
+    fn the_human_walks_to_the_water_then_drinks_until_quenched() {
+        let mut world = thirsty_world();
+        assert_eq!(run(&mut world, 5), vec![
+            (Position::new(1, 0), 44, Some(MOVE_LEFT)),
+            (Position::new(0, 0), 43, Some(MOVE_LEFT)),
+            (Position::new(0, 0), 72, Some(DRINK)),
+            // Above the threshold of `Check`, `Holds` keeps the drinking loop going.
+            (Position::new(0, 0), 100, Some(DRINK)),
+            (Position::new(0, 0), 99, Some(WAIT))
+        ]);
+    }
+
+    #[test]
+    fn the_tree_fails_over_to_idle_without_water() {
+        let mut world = World::from_cells(Grid::new(3, 1, Element::Grass(1.0)), 1);
+        let behaviour : SharedBehaviour = Arc::new(RwLock::new(BTBehaviour::survival()));
+        let mut human = Human::new(1, 0, behaviour, world.environment.clone());
+        human.thirst.value = 45;
+        world.add_human(human);
+        assert!(run(&mut world, 3).iter().all(|(position, _, action)| *position == Position::new(1, 0) && *action == Some(WAIT)));
+    }
+}
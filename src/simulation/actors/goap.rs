@@ -0,0 +1,326 @@
+use crate::learning::qlearning::Agent;
+use crate::simulation::actors::behaviour::{move_towards, Behaviour, DRINK, EAT, WAIT};
+use crate::simulation::actors::humans::{Human, Need};
+use crate::simulation::world::{Element, Resource};
+use crate::types::Position;
+
+use serde::{Deserialize, Serialize};
+
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+
+/// Symbolic facts the planner reasons about.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Fact {
+    Thirsty,
+    Hungry,
+    Tired,
+    AtWater,
+    AtFood
+}
+
+/// Set of facts currently true.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct WorldState(u32);
+
+impl WorldState {
+    pub fn has(&self, fact : Fact) -> bool {
+        self.0 & (1 << fact as u32) != 0
+    }
+
+    pub fn with(self, fact : Fact, value : bool) -> WorldState {
+        if value {
+            WorldState(self.0 | (1 << fact as u32))
+        } else {
+            WorldState(self.0 & !(1 << fact as u32))
+        }
+    }
+
+    pub fn satisfies(&self, conditions : &[(Fact, bool)]) -> bool {
+        conditions.iter().all(|(fact, value)| self.has(*fact) == *value)
+    }
+}
+
+/// How an abstract action is carried out in the simulation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ActionKind {
+    /// Walks to the closest cell holding the resource, costs one per cell.
+    MoveTo(Resource),
+    Drink,
+    Eat
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GoapAction {
+    pub name : String,
+    pub kind : ActionKind,
+    pub preconditions : Vec<(Fact, bool)>,
+    pub effects : Vec<(Fact, bool)>,
+    pub cost : f64
+}
+
+impl GoapAction {
+    pub fn new(name : &str, kind : ActionKind, preconditions : Vec<(Fact, bool)>, effects : Vec<(Fact, bool)>, cost : f64) -> GoapAction {
+        GoapAction { name : name.to_string(), kind, preconditions, effects, cost }
+    }
+
+    pub fn apply(&self, state : WorldState) -> WorldState {
+        self.effects.iter().fold(state, |state, (fact, value)| state.with(*fact, *value))
+    }
+}
+
+/// Distances from the human to the resources, used to price moves.
+#[derive(Clone, Debug, Default)]
+pub struct Context {
+    pub distances : HashMap<Resource, i32>
+}
+
+impl Context {
+    pub fn observe(human : &Human) -> Context {
+        let distances = [Resource::Water, Resource::Food]
+        .iter()
+        .filter_map(|resource| human.find_closest(resource.element())
+            .map(|target| (*resource, target.manhattan_dist(&human.position))))
+        .collect();
+        Context { distances }
+    }
+}
+
+/// Cheapest sequence of actions reaching a goal.
+#[derive(Clone, Debug, Default)]
+pub struct Plan {
+    /// Indices into `Planner::actions`.
+    pub actions : Vec<usize>,
+    pub cost : f64
+}
+
+struct Node {
+    cost : f64,
+    state : WorldState
+}
+
+impl PartialEq for Node {
+    fn eq(&self, other : &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Node {}
+
+impl PartialOrd for Node {
+    fn partial_cmp(&self, other : &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Node {
+    // Reversed so that `BinaryHeap` pops the cheapest node first.
+    fn cmp(&self, other : &Self) -> Ordering {
+        other.cost.partial_cmp(&self.cost).unwrap_or(Ordering::Equal)
+            .then_with(|| self.state.cmp(&other.state))
+    }
+}
+
+pub struct Planner {
+    pub actions : Vec<GoapAction>,
+    /// Longest plan searched for.
+    pub max_depth : usize
+}
+
+impl Default for Planner {
+    fn default() -> Self {
+        Planner {
+            actions : vec![
+                GoapAction::new("Move to water", ActionKind::MoveTo(Resource::Water),
+                    vec![(Fact::AtWater, false)], vec![(Fact::AtWater, true), (Fact::AtFood, false)], 1.0),
+                GoapAction::new("Move to food", ActionKind::MoveTo(Resource::Food),
+                    vec![(Fact::AtFood, false)], vec![(Fact::AtFood, true), (Fact::AtWater, false)], 1.0),
+                GoapAction::new("Drink", ActionKind::Drink,
+                    vec![(Fact::AtWater, true)], vec![(Fact::Thirsty, false)], 1.0),
+                GoapAction::new("Eat", ActionKind::Eat,
+                    vec![(Fact::AtFood, true)], vec![(Fact::Hungry, false)], 1.0),
+            ],
+            max_depth : 8
+        }
+    }
+}
+
+impl Planner {
+    pub fn cost(&self, action : usize, context : &Context) -> Option<f64> {
+        let action = &self.actions[action];
+        match action.kind {
+            ActionKind::MoveTo(resource) => context.distances.get(&resource)
+                .map(|distance| action.cost + *distance as f64),
+            _ => Some(action.cost)
+        }
+    }
+
+    /// Deterministic model of the abstract dynamics : the state reached by
+    /// taking `action` in `state` and its cost, or `None` if the action is
+    /// not applicable. Meant for planning based learning experiments.
+    pub fn transition(&self, state : WorldState, action : usize, context : &Context) -> Option<(WorldState, f64)> {
+        if !state.satisfies(&self.actions[action].preconditions) {
+            return None;
+        }
+        self.cost(action, context).map(|cost| (self.actions[action].apply(state), cost))
+    }
+
+    /// Uniform cost search from `start` to any state satisfying `goal`.
+    pub fn plan(&self, start : WorldState, goal : &[(Fact, bool)], context : &Context) -> Option<Plan> {
+        let mut best : HashMap<WorldState, (f64, Vec<usize>)> = HashMap::new();
+        let mut open = BinaryHeap::new();
+        best.insert(start, (0.0, Vec::new()));
+        open.push(Node { cost : 0.0, state : start });
+
+        while let Some(Node { cost, state }) = open.pop() {
+            if cost > best[&state].0 {
+                continue;
+            }
+            let actions = best[&state].1.clone();
+            if state.satisfies(goal) {
+                return Some(Plan { actions, cost });
+            }
+            if actions.len() >= self.max_depth {
+                continue;
+            }
+
+            for action in 0..self.actions.len() {
+                if let Some((next, step_cost)) = self.transition(state, action, context) {
+                    let next_cost = cost + step_cost;
+                    if best.get(&next).is_none_or(|(known, _)| next_cost < *known) {
+                        let mut next_actions = actions.clone();
+                        next_actions.push(action);
+                        best.insert(next, (next_cost, next_actions));
+                        open.push(Node { cost : next_cost, state : next });
+                    }
+                }
+            }
+        }
+        None
+    }
+}
+
+/// Goal oriented behaviour : plans towards relieving the most urgent need and
+/// carries out the first step of the plan, replanning every step.
+pub struct GoapBehaviour {
+    pub planner : Planner,
+    /// Needs below this value become goals.
+    pub urgency_threshold : i32,
+    /// Needs being refilled stay goals until they reach this value.
+    pub satiety_threshold : i32
+}
+
+impl GoapBehaviour {
+    pub fn new(planner : Planner, urgency_threshold : i32, satiety_threshold : i32) -> GoapBehaviour {
+        GoapBehaviour { planner, urgency_threshold, satiety_threshold }
+    }
+
+    pub fn observe(&self, human : &Human) -> WorldState {
+        let environment = human.environment.read().unwrap();
+        let element = environment.cells[human.position];
+        let at_water = matches!(element, Element::Water(_));
+        let at_food = matches!(element, Element::Tree(_));
+        // Once at a resource, the need is refilled up to the satiety threshold.
+        let pressing = |need : &Need, refilling : bool| need.value < self.urgency_threshold
+            || (refilling && need.value < self.satiety_threshold);
+        WorldState::default()
+            .with(Fact::Thirsty, pressing(&human.thirst, at_water))
+            .with(Fact::Hungry, pressing(&human.hunger, at_food))
+            .with(Fact::Tired, pressing(&human.energy, false))
+            .with(Fact::AtWater, at_water)
+            .with(Fact::AtFood, at_food)
+    }
+
+    /// Goals of relieving each pressing need, the ones that can be relieved
+    /// in place first, then the most urgent. Being tired is not a goal, no
+    /// action relieves it yet.
+    pub fn goals(&self, human : &Human, state : WorldState) -> Vec<Fact> {
+        let mut needs : Vec<(Fact, &Need, bool)> = vec![
+            (Fact::Thirsty, &human.thirst, state.has(Fact::AtWater)),
+            (Fact::Hungry, &human.hunger, state.has(Fact::AtFood))
+        ];
+        needs.retain(|(fact, _, _)| state.has(*fact));
+        needs.sort_by_key(|(_, need, in_place)| (!in_place, need.value - need.min_value));
+        needs.into_iter().map(|(fact, _, _)| fact).collect()
+    }
+
+    /// Plan for the most urgent need that can be relieved.
+    pub fn current_plan(&self, human : &Human) -> Option<Plan> {
+        let state = self.observe(human);
+        let context = Context::observe(human);
+        self.goals(human, state)
+        .into_iter()
+        .find_map(|goal| self.planner.plan(state, &[(goal, false)], &context))
+    }
+
+    fn execute(&self, human : &Human, action : usize) -> usize {
+        match self.planner.actions[action].kind {
+            ActionKind::MoveTo(resource) => human.find_closest(resource.element())
+                .map_or(WAIT, |target : Position| move_towards(human.position, target)),
+            ActionKind::Drink => DRINK,
+            ActionKind::Eat => EAT
+        }
+    }
+}
+
+impl Default for GoapBehaviour {
+    fn default() -> Self {
+        Self::new(Planner::default(), 60, 90)
+    }
+}
+
+impl Behaviour for GoapBehaviour {
+    fn predict_action(&self, human : &Human) -> usize {
+        match self.current_plan(human).and_then(|plan| plan.actions.first().copied()) {
+            Some(action) => self.execute(human, action),
+            None => WAIT
+        }
+    }
+
+    fn step(&self, human : &mut Human) {
+        human.do_action(self.predict_action(human));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::actors::behaviour::{SharedBehaviour, MOVE_LEFT};
+    use crate::simulation::world::World;
+    use crate::types::Grid;
+    use std::sync::{Arc, RwLock};
+
+    /// Thirsty human two cells right of `left`.
+    fn thirsty_human(left : Element) -> (World, Human) {
+        let mut cells = Grid::new(3, 1, Element::Grass(1.0));
+        cells[Position::new(0, 0)] = left;
+        let world = World::from_cells(cells, 1);
+        let behaviour : SharedBehaviour = Arc::new(RwLock::new(GoapBehaviour::default()));
+        let mut human = Human::new(2, 0, behaviour, world.environment.clone());
+        human.thirst.value = 40;
+        (world, human)
+    }
+
+    #[test]
+    fn thirsty_humans_plan_to_walk_to_the_water_and_drink() {
+        let (_world, human) = thirsty_human(Element::Water(1.0));
+        let goap = GoapBehaviour::default();
+        let plan = goap.current_plan(&human).unwrap();
+        let names : Vec<&str> = plan.actions.iter().map(|action| goap.planner.actions[*action].name.as_str()).collect();
+        assert_eq!(names, vec!["Move to water", "Drink"]);
+        // Two cells away, on top of the unit costs of both actions.
+        assert_eq!(plan.cost, 4.0);
+        assert_eq!(goap.predict_action(&human), MOVE_LEFT);
+    }
+
+    #[test]
+    fn there_is_no_plan_without_the_resource() {
+        let (_world, human) = thirsty_human(Element::Tree(1.0));
+        let goap = GoapBehaviour::default();
+        let state = goap.observe(&human);
+        assert_eq!(goap.goals(&human, state), vec![Fact::Thirsty]);
+        assert!(goap.planner.plan(state, &[(Fact::Thirsty, false)], &Context::observe(&human)).is_none());
+        assert!(goap.current_plan(&human).is_none());
+        assert_eq!(goap.predict_action(&human), WAIT);
+    }
+}
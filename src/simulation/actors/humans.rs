@@ -1,9 +1,13 @@
 
-use crate::simulation::actors::behaviour::{Behaviour, QLBehaviour};
+use crate::simulation::actors::behaviour::{PendingStep, SharedBehaviour};
+use crate::simulation::events::{DeathCause, EventKind};
+use crate::simulation::actors::reward::{RewardBreakdown, RewardConfig, RewardFunction};
 use crate::simulation::world::{Element, Environment};
 
 use crate::learning::qlearning::Agent;
-use crate::types::Position;
+use crate::types::{rng_from_seed, Grid, Position, SimRng};
+
+use serde::{Deserialize, Serialize};
 
 use std::sync::{Arc, RwLock};
 use std::cmp::max;
@@ -17,12 +21,27 @@ pub struct Human {
     pub energy : Need,
     pub money : Need,
     pub alive : bool,
-    pub behaviour : Arc<RwLock<QLBehaviour>>,
-    pub environment : Arc<RwLock<Environment>>
+    pub behaviour : SharedBehaviour,
+    pub environment : Arc<RwLock<Environment>>,
+    pub reward : Arc<dyn RewardFunction>,
+    /// Reward of the learning step of the last tick, by component, empty if
+    /// the human did not learn.
+    pub last_reward : RewardBreakdown,
+    /// Source of the random choices of the human and its behaviour.
+    pub rng : SimRng,
+    /// Action carried out during the last step, `None` if it did not act.
+    pub last_action : Option<usize>,
+    /// Action of the last step still to be learnt from, see `QLBehaviour`.
+    pub pending_step : Option<PendingStep>,
+    /// Cells every simulated life starts from, put back by `Agent::reset`.
+    /// `None` for the humans of a live world, which never reset.
+    pub episode_cells : Option<Grid<Element>>,
+    /// Events of the current step, published by the world after it.
+    pub events : Vec<EventKind>
 }
 
 impl Human {
-    pub fn new(x : i32, y : i32, behaviour : Arc<RwLock<QLBehaviour>>, environment :Arc<RwLock<Environment>>) -> Self{
+    pub fn new(x : i32, y : i32, behaviour : SharedBehaviour, environment :Arc<RwLock<Environment>>) -> Self{
         Human{
             position : Position{x, y},
             age : 0,
@@ -31,12 +50,21 @@ impl Human {
             energy : Need{value : 100, min_value : 0, max_value : 100},
             money : Need{value : 0, min_value : 0, max_value : i32::MAX},
             alive : true,
-            behaviour : behaviour.clone(),
-            environment
+            behaviour,
+            environment,
+            reward : Arc::new(RewardConfig::default()),
+            last_reward : RewardBreakdown::default(),
+            rng : rng_from_seed(None),
+            last_action : None,
+            pending_step : None,
+            episode_cells : None,
+            events : Vec::new()
         }
     }
 
     pub fn step_time(&mut self) {
+        self.last_action = None;
+        self.last_reward.terms.clear();
         if !self.alive {
             return;
         }
@@ -45,12 +73,14 @@ impl Human {
             self.hunger.value = max(self.hunger.value - 1, 0);
             if self.hunger.value <= 0 {
                 self.alive = false;
+                self.events.push(EventKind::HumanDied { cause : DeathCause::Hunger });
             }
         }
         {
             self.thirst.value = max(self.thirst.value - 1, 0);
-            if self.thirst.value <= 0 {
+            if self.thirst.value <= 0 && self.alive {
                 self.alive = false;
+                self.events.push(EventKind::HumanDied { cause : DeathCause::Thirst });
             }
         }
         {
@@ -65,32 +95,30 @@ impl Human {
         self.step();
     }
 
-    fn find_closest(&self, element : Element) -> Option<Position> {
+    /// Closest cell holding the same kind of element, searched in growing
+    /// manhattan rings around the human.
+    pub fn find_closest(&self, element : Element) -> Option<Position> {
         let environment = &self.environment.read().unwrap();
         let cells = &environment.cells;
         
-        let (m, n)= environment.world_limits;
+        let (m, n) = (cells.width(), cells.height());
 
         let curr_x = self.position.x;
         let curr_y = self.position.y;
 
-        let max_dist = curr_x
-            .max(curr_y
-            .max((m as i32 - curr_x)
-            .max((n as i32 - curr_y))));
+        let max_dist = curr_x.max(m as i32 - 1 - curr_x)
+            + curr_y.max(n as i32 - 1 - curr_y);
         
-        let mut to_test = Vec::new();
-        to_test.push(self.position);
+        let mut to_test = vec![self.position];
 
-                
-        for i in 0..max_dist {
-            
+        for i in 1..=max_dist + 1 {
             for p in to_test.iter() {
-                if p.x < 0 || p.y < 0 || p.x >= m as i32 || p.y >= n as i32 {
-                    continue;
-                }
+                let cell = match cells.get(*p) {
+                    Some(cell) => cell,
+                    None => continue
+                };
     
-                match cells[p.x as usize][p.y as usize] {
+                match cell {
                     Element::Grass(_) => if matches!(element, Element::Grass(_)) {return Some(*p)},
                     Element::Water(_) => if matches!(element, Element::Water(_)) {return Some(*p)},
                     Element::Tree(_) => if matches!(element, Element::Tree(_)) {return Some(*p)},
@@ -102,16 +130,17 @@ impl Human {
             to_test.clear();
             for j in 0..i {
                 to_test.push(Position::new(i - j + curr_x, j + curr_y));
-                to_test.push(Position::new(-i + j + curr_x, j + curr_y));
-                to_test.push(Position::new(i - j + curr_x, -j + curr_y));
+                to_test.push(Position::new(-j + curr_x, i - j + curr_y));
                 to_test.push(Position::new(-i + j + curr_x, -j + curr_y));
+                to_test.push(Position::new(j + curr_x, -i + j + curr_y));
             }
         }
 
-        return None;
+        None
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Need {
     pub value : i32, 
     pub min_value : i32,
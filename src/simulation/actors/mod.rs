@@ -1,2 +1,6 @@
 pub mod behaviour;
-pub mod humans;
\ No newline at end of file
+pub mod behaviour_tree;
+pub mod goap;
+pub mod humans;
+pub mod reward;
+pub mod utility;
\ No newline at end of file
@@ -0,0 +1,265 @@
+use crate::simulation::actors::humans::{Human, Need};
+use crate::simulation::world::{Element, Resource};
+
+use serde::{Deserialize, Serialize};
+
+use std::fmt;
+
+/// Contribution of one named component to a reward.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RewardTerm {
+    pub name : String,
+    pub value : f64
+}
+
+/// Reward of a step, split by the components that produced it.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct RewardBreakdown {
+    pub terms : Vec<RewardTerm>
+}
+
+impl RewardBreakdown {
+    pub fn push(&mut self, name : &str, value : f64) {
+        self.terms.push(RewardTerm { name : name.to_string(), value });
+    }
+
+    pub fn total(&self) -> f64 {
+        self.terms.iter().map(|term| term.value).sum()
+    }
+
+    pub fn get(&self, name : &str) -> Option<f64> {
+        self.terms.iter().find(|term| term.name == name).map(|term| term.value)
+    }
+}
+
+impl fmt::Display for RewardBreakdown {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.total())?;
+        for term in self.terms.iter().filter(|term| term.value != 0.0) {
+            write!(f, " {}={}", term.name, term.value)?;
+        }
+        Ok(())
+    }
+}
+
+pub trait RewardFunction : Send + Sync {
+    /// Reward of the state `human` is in, component by component.
+    fn reward(&self, human : &Human) -> RewardBreakdown;
+
+    /// Potential `Φ` of the state for reward shaping, 0 disables shaping.
+    fn potential(&self, _human : &Human) -> f64 {
+        0.0
+    }
+
+    /// Discount used by the shaping term, must be the one of the learner.
+    fn gamma(&self) -> f64 {
+        1.0
+    }
+
+    /// Full reward of a step : the immediate reward of the action, the reward
+    /// of the state reached and the shaping term `γΦ(s') - Φ(s)`.
+    ///
+    /// Shaping of this form changes the return of every policy by the same
+    /// `-Φ(s0)`, so the optimal policy is preserved (Ng, Harada and Russell,
+    /// 1999). This relies on `Φ` being 0 in terminal states, which is enforced
+    /// here for dead humans, and on the learner not bootstrapping from them.
+    /// Lives cut at the age cap are not terminal, `Φ` is kept there.
+    fn step_reward(&self, human : &Human, action_reward : f64, previous_potential : f64) -> RewardBreakdown {
+        let mut breakdown = self.reward(human);
+        breakdown.terms.insert(0, RewardTerm { name : "action".to_string(), value : action_reward });
+        let potential = if human.alive { self.potential(human) } else { 0.0 };
+        let shaping = self.gamma() * potential - previous_potential;
+        if shaping != 0.0 {
+            breakdown.push("shaping", shaping);
+        }
+        breakdown
+    }
+}
+
+/// Reward of a need, by the band its value falls in : `rewards[i]` while the
+/// value is above `thresholds[i]`, the last reward below every threshold.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct NeedLevels {
+    pub thresholds : Vec<i32>,
+    pub rewards : Vec<f64>
+}
+
+impl NeedLevels {
+    pub fn reward(&self, need : &Need) -> f64 {
+        let band = self.thresholds.iter()
+        .position(|threshold| need.value > *threshold)
+        .unwrap_or(self.thresholds.len());
+        self.rewards.get(band).copied().unwrap_or(0.0)
+    }
+}
+
+impl Default for NeedLevels {
+    fn default() -> Self {
+        NeedLevels { thresholds : vec![80, 50, 20], rewards : vec![1.0, 0.0, -30.0, -100.0] }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Component {
+    /// -1 when the human died, the other components are then skipped.
+    Death,
+    Hunger(NeedLevels),
+    Thirst(NeedLevels),
+    /// 1 while standing on water or trees.
+    OnResource,
+    /// Steps lived beyond `after`.
+    Age { after : u32 },
+    /// 1 per step alive.
+    Alive
+}
+
+impl Component {
+    pub fn value(&self, human : &Human) -> f64 {
+        match self {
+            Component::Death => if human.alive { 0.0 } else { -1.0 },
+            Component::Hunger(levels) => levels.reward(&human.hunger),
+            Component::Thirst(levels) => levels.reward(&human.thirst),
+            Component::OnResource => {
+                let environment = human.environment.read().unwrap();
+                match environment.cells[human.position] {
+                    Element::Water(_) | Element::Tree(_) => 1.0,
+                    _ => 0.0
+                }
+            },
+            Component::Age { after } => (human.age as f64 - *after as f64).max(0.0),
+            Component::Alive => 1.0
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct WeightedComponent {
+    pub name : String,
+    pub component : Component,
+    pub weight : f64
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Potential {
+    /// Mean fill of hunger and thirst, in `[0, 1]`.
+    Needs,
+    /// Minus the distance to the closest resource backing the lowest need,
+    /// relative to the size of the world.
+    Proximity
+}
+
+impl Potential {
+    pub fn value(&self, human : &Human) -> f64 {
+        match self {
+            Potential::Needs => (fill(&human.hunger) + fill(&human.thirst)) / 2.0,
+            Potential::Proximity => {
+                let resource = if human.thirst.value <= human.hunger.value { Resource::Water } else { Resource::Food };
+                let (width, height) = {
+                    let cells = &human.environment.read().unwrap().cells;
+                    (cells.width(), cells.height())
+                };
+                human.find_closest(resource.element())
+                .map_or(-1.0, |target| -(target.manhattan_dist(&human.position) as f64) / (width + height) as f64)
+            }
+        }
+    }
+}
+
+fn fill(need : &Need) -> f64 {
+    (need.value - need.min_value) as f64 / (need.max_value - need.min_value).max(1) as f64
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Shaping {
+    /// Has to match the discount of the learner for the guarantee to hold,
+    /// scenarios check it against theirs.
+    pub gamma : f64,
+    pub potentials : Vec<(Potential, f64)>
+}
+
+/// Reward made of named, weighted components, optionally shaped.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RewardConfig {
+    pub components : Vec<WeightedComponent>,
+    #[serde(default)]
+    pub shaping : Option<Shaping>
+}
+
+impl RewardConfig {
+    pub fn component(mut self, name : &str, component : Component, weight : f64) -> RewardConfig {
+        self.components.push(WeightedComponent { name : name.to_string(), component, weight });
+        self
+    }
+}
+
+impl Default for RewardConfig {
+    /// The reward the humans were originally trained with.
+    fn default() -> Self {
+        RewardConfig { components : Vec::new(), shaping : None }
+            .component("death", Component::Death, 1000.0)
+            .component("hunger", Component::Hunger(NeedLevels::default()), 1.0)
+            .component("thirst", Component::Thirst(NeedLevels::default()), 1.0)
+            .component("resource", Component::OnResource, 1.0)
+            .component("age", Component::Age { after : 100 }, 10000.0)
+    }
+}
+
+impl RewardFunction for RewardConfig {
+    fn reward(&self, human : &Human) -> RewardBreakdown {
+        let mut breakdown = RewardBreakdown::default();
+        for weighted in self.components.iter() {
+            if !human.alive && weighted.component != Component::Death {
+                continue;
+            }
+            breakdown.push(&weighted.name, weighted.weight * weighted.component.value(human));
+        }
+        breakdown
+    }
+
+    fn potential(&self, human : &Human) -> f64 {
+        self.shaping.as_ref().map_or(0.0, |shaping| shaping.potentials.iter()
+            .map(|(potential, weight)| weight * potential.value(human))
+            .sum())
+    }
+
+    fn gamma(&self) -> f64 {
+        self.shaping.as_ref().map_or(1.0, |shaping| shaping.gamma)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::learning::qlearning::Agent;
+    use crate::simulation::actors::behaviour::{QLBehaviour, SharedBehaviour, WAIT};
+    use crate::simulation::world::World;
+    use crate::types::Grid;
+
+    use std::sync::{Arc, RwLock};
+
+    #[test]
+    fn shaping_telescopes_to_minus_the_first_potential() {
+        let gamma = 0.9;
+        let reward = RewardConfig {
+            components : Vec::new(),
+            shaping : Some(Shaping { gamma, potentials : vec![(Potential::Needs, 2.0)] })
+        };
+        // Nothing to drink or eat, the human dies within a hundred steps.
+        let world = World::from_cells(Grid::new(2, 2, Element::Grass(1.0)), 1);
+        let behaviour : SharedBehaviour = Arc::new(RwLock::new(QLBehaviour::new()));
+        let mut human = Human::new(0, 0, behaviour, world.environment.clone());
+        human.reward = Arc::new(reward.clone());
+        human.thirst.value = 10;
+        let first_potential = reward.potential(&human);
+
+        let mut discounted = 0.0;
+        let mut discount = 1.0;
+        let mut dead = false;
+        while !dead {
+            (_, _, dead) = human.simulate_action(WAIT);
+            discounted += discount * human.last_reward.get("shaping").unwrap_or(0.0);
+            discount *= gamma;
+        }
+        assert!((discounted + first_potential).abs() < 1e-9);
+    }
+}
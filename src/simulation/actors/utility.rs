@@ -0,0 +1,118 @@
+use crate::learning::qlearning::Agent;
+use crate::simulation::actors::behaviour::{move_towards, Behaviour, DRINK, EAT, WAIT};
+use crate::simulation::actors::humans::{Human, Need};
+use crate::simulation::world::Resource;
+
+use serde::{Deserialize, Serialize};
+
+/// Maps an urgency in `[0, 1]` (0 when the need is full, 1 when it is empty)
+/// to a score in `[0, 1]`.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Curve {
+    Linear,
+    /// `urgency^exponent`, stays low until the need gets critical.
+    Power(f64),
+    /// Smooth step centred on `midpoint`.
+    Logistic { midpoint : f64, steepness : f64 }
+}
+
+impl Curve {
+    pub fn evaluate(&self, urgency : f64) -> f64 {
+        let urgency = urgency.clamp(0.0, 1.0);
+        match self {
+            Curve::Linear => urgency,
+            Curve::Power(exponent) => urgency.powf(*exponent),
+            Curve::Logistic { midpoint, steepness } => 1.0 / (1.0 + (-steepness * (urgency - midpoint)).exp())
+        }
+    }
+}
+
+/// Weighted response curve of one need.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Consideration {
+    pub weight : f64,
+    pub curve : Curve
+}
+
+impl Consideration {
+    pub fn score(&self, need : &Need) -> f64 {
+        let range = (need.max_value - need.min_value).max(1) as f64;
+        let urgency = (need.max_value - need.value) as f64 / range;
+        self.weight * self.curve.evaluate(urgency)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct UtilityWeights {
+    pub thirst : Consideration,
+    pub hunger : Consideration,
+    /// Tiredness, lowers the appeal of walking somewhere.
+    pub energy : Consideration,
+    /// Score lost per cell between the human and a resource.
+    pub distance : f64,
+    /// Flat score of waiting, actions scoring below it are not worth doing.
+    pub idle : f64
+}
+
+impl Default for UtilityWeights {
+    fn default() -> Self {
+        UtilityWeights {
+            thirst : Consideration { weight : 1.0, curve : Curve::Logistic { midpoint : 0.5, steepness : 10.0 } },
+            hunger : Consideration { weight : 1.0, curve : Curve::Logistic { midpoint : 0.5, steepness : 10.0 } },
+            energy : Consideration { weight : 0.2, curve : Curve::Power(2.0) },
+            distance : 0.01,
+            idle : 0.1
+        }
+    }
+}
+
+/// Non learned baseline : scores going to and consuming each resource from
+/// the needs of the human and the distance to the resource, then picks the
+/// best scoring action.
+pub struct UtilityBehaviour {
+    pub weights : UtilityWeights
+}
+
+impl UtilityBehaviour {
+    pub fn new(weights : UtilityWeights) -> UtilityBehaviour {
+        UtilityBehaviour { weights }
+    }
+
+    /// Score of satisfying the need backed by `resource`, and the action
+    /// leading to it.
+    fn score(&self, human : &Human, resource : Resource) -> Option<(f64, usize)> {
+        let (need, consume) = match resource {
+            Resource::Water => (self.weights.thirst.score(&human.thirst), DRINK),
+            Resource::Food => (self.weights.hunger.score(&human.hunger), EAT)
+        };
+        let target = human.find_closest(resource.element())?;
+        if target == human.position {
+            return Some((need, consume));
+        }
+
+        let distance = target.manhattan_dist(&human.position) as f64;
+        let fatigue = 1.0 - self.weights.energy.score(&human.energy).min(1.0);
+        Some((need * fatigue - self.weights.distance * distance, move_towards(human.position, target)))
+    }
+}
+
+impl Default for UtilityBehaviour {
+    fn default() -> Self {
+        Self::new(UtilityWeights::default())
+    }
+}
+
+impl Behaviour for UtilityBehaviour {
+    fn predict_action(&self, human : &Human) -> usize {
+        [Resource::Water, Resource::Food]
+        .iter()
+        .filter_map(|resource| self.score(human, *resource))
+        .chain(std::iter::once((self.weights.idle, WAIT)))
+        .max_by(|(a, _), (b, _)| a.total_cmp(b))
+        .unwrap().1
+    }
+
+    fn step(&self, human : &mut Human) {
+        human.do_action(self.predict_action(human));
+    }
+}
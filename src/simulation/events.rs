@@ -0,0 +1,75 @@
+use crate::simulation::world::Resource;
+use crate::types::Position;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DeathCause {
+    Hunger,
+    Thirst
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum EventKind {
+    /// The human joined the world.
+    HumanAdded { position : Position },
+    /// The human was taken out of the world, the next ones moved down by one.
+    HumanRemoved { position : Position },
+    HumanDied { cause : DeathCause },
+    /// A drink or a meal, `amount` is how much of the need it refilled.
+    Consumed { resource : Resource, amount : i32 },
+    /// The cell ran out and became empty.
+    ResourceDepleted { resource : Resource, position : Position },
+    Moved { from : Position, to : Position },
+    /// Tried to move out of the world and stayed at `position`.
+    BlockedByWall { position : Position }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Event {
+    pub tick : u64,
+    /// Index of the human in `World::humans`.
+    pub human : usize,
+    pub kind : EventKind
+}
+
+pub trait Subscriber : Send {
+    fn notify(&mut self, event : &Event);
+}
+
+impl<F : FnMut(&Event) + Send> Subscriber for F {
+    fn notify(&mut self, event : &Event) {
+        self(event)
+    }
+}
+
+/// Handle returned by `EventBus::subscribe`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct SubscriberId(usize);
+
+/// Dispatches the events of a world to its subscribers, in the order they
+/// subscribed.
+#[derive(Default)]
+pub struct EventBus {
+    subscribers : Vec<(SubscriberId, Box<dyn Subscriber>)>,
+    next_id : usize
+}
+
+impl EventBus {
+    pub fn subscribe<S : Subscriber + 'static>(&mut self, subscriber : S) -> SubscriberId {
+        let id = SubscriberId(self.next_id);
+        self.next_id += 1;
+        self.subscribers.push((id, Box::new(subscriber)));
+        id
+    }
+
+    pub fn unsubscribe(&mut self, id : SubscriberId) {
+        self.subscribers.retain(|(subscriber, _)| *subscriber != id);
+    }
+
+    pub fn publish(&mut self, event : &Event) {
+        for (_, subscriber) in self.subscribers.iter_mut() {
+            subscriber.notify(event);
+        }
+    }
+}
@@ -0,0 +1,40 @@
+use crate::simulation::world::World;
+
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Steps a world without any display, for batches of simulations.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct HeadlessRunner {
+    /// Number of steps, unbounded if `None`.
+    pub ticks : Option<u64>,
+    /// Time between two steps, as fast as possible if `None`.
+    pub time_step : Option<Duration>,
+    /// Stops as soon as every human is dead.
+    pub stop_when_extinct : bool
+}
+
+impl HeadlessRunner {
+    /// Steps `world`, calling `on_tick` with the number of the step after each
+    /// one. Returns the number of steps run.
+    pub fn run<F : FnMut(u64, &World)>(&self, world : &mut World, mut on_tick : F) -> u64 {
+        let mut tick = 0;
+        let mut next_step = Instant::now();
+        while self.ticks.is_none_or(|ticks| tick < ticks) {
+            if self.stop_when_extinct && world.humans.iter().all(|human| !human.alive) {
+                break;
+            }
+            world.step_time();
+            tick += 1;
+            on_tick(tick, world);
+
+            // Steps are scheduled from a fixed origin so that slow ticks do
+            // not make the whole run drift.
+            if let Some(time_step) = self.time_step {
+                next_step += time_step;
+                thread::sleep(next_step.saturating_duration_since(Instant::now()));
+            }
+        }
+        tick
+    }
+}
@@ -0,0 +1,114 @@
+//! Text map files : one line per row of the world, one character per cell.
+//!
+//! | Character | Element |
+//! |-----------|---------|
+//! | `.`       | None    |
+//! | `G`       | Grass   |
+//! | `T`       | Tree    |
+//! | `W`       | Water   |
+//! | `H`       | House   |
+
+use crate::simulation::world::Element;
+use crate::types::{Grid, Position};
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+pub fn element(symbol : char) -> Option<Element> {
+    match symbol {
+        '.' => Some(Element::None),
+        'G' => Some(Element::Grass(1.0)),
+        'T' => Some(Element::Tree(1.0)),
+        'W' => Some(Element::Water(1.0)),
+        'H' => Some(Element::House(1.0)),
+        _ => None
+    }
+}
+
+pub fn symbol(element : &Element) -> char {
+    match element {
+        Element::None => '.',
+        Element::Grass(_) => 'G',
+        Element::Tree(_) => 'T',
+        Element::Water(_) => 'W',
+        Element::House(_) => 'H'
+    }
+}
+
+/// Cells described by `text`. Blank lines are only allowed before and after
+/// the rows.
+pub fn parse(text : &str) -> io::Result<Grid<Element>> {
+    let lines : Vec<&str> = text.lines().map(|line| line.trim_end()).collect();
+    let start = lines.iter().position(|line| !line.is_empty()).unwrap_or(lines.len());
+    let end = lines.iter().rposition(|line| !line.is_empty()).map_or(start, |last| last + 1);
+    let rows = &lines[start..end];
+    let width = match rows.first() {
+        Some(row) => row.chars().count(),
+        None => return Err(io::Error::new(io::ErrorKind::InvalidData, "empty map"))
+    };
+
+    let mut cells = Grid::new(width, rows.len(), Element::None);
+    for (y, row) in rows.iter().enumerate() {
+        if row.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, format!("map row {} is blank", y)));
+        }
+        if row.chars().count() != width {
+            return Err(io::Error::new(io::ErrorKind::InvalidData,
+                format!("map row {} has {} cells, expected {}", y, row.chars().count(), width)));
+        }
+        for (x, c) in row.chars().enumerate() {
+            cells[Position::new(x as i32, y as i32)] = element(c).ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData,
+                format!("unknown map cell '{}' at ({}, {})", c, x, y)))?;
+        }
+    }
+    Ok(cells)
+}
+
+pub fn load<P : AsRef<Path>>(path : P) -> io::Result<Grid<Element>> {
+    parse(&fs::read_to_string(path)?)
+}
+
+/// Text of the cells, read back by `parse`.
+pub fn format(cells : &Grid<Element>) -> String {
+    let mut text = String::with_capacity((cells.width() + 1) * cells.height());
+    for y in 0..cells.height() as i32 {
+        text.extend((0..cells.width() as i32).map(|x| symbol(&cells[Position::new(x, y)])));
+        text.push('\n');
+    }
+    text
+}
+
+pub fn save<P : AsRef<Path>>(path : P, cells : &Grid<Element>) -> io::Result<()> {
+    fs::write(path, format(cells))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_reads_back() {
+        let text = "GGT\nW.H\n";
+        let cells = parse(text).unwrap();
+        assert_eq!((cells.width(), cells.height()), (3, 2));
+        assert_eq!(cells[Position::new(2, 0)], Element::Tree(1.0));
+        assert_eq!(cells[Position::new(0, 1)], Element::Water(1.0));
+        assert_eq!(format(&cells), text);
+        assert_eq!(parse(&format(&cells)).unwrap(), cells);
+    }
+
+    #[test]
+    fn invalid_maps_are_rejected() {
+        assert!(parse("").is_err());
+        assert!(parse("\n  \n").is_err());
+        assert!(parse("GG\nG\n").is_err());
+        assert!(parse("GX\n").is_err());
+        assert!(parse("GG\n\nGG\n").is_err());
+    }
+
+    #[test]
+    fn blank_lines_around_the_rows_are_skipped() {
+        assert_eq!(parse("\nGT\nWH\n\n  \n").unwrap(), parse("GT\nWH").unwrap());
+    }
+}
@@ -1,2 +1,11 @@
 pub mod actors;
+pub mod events;
+pub mod headless;
+pub mod map;
+pub mod render;
+pub mod replay;
+pub mod scheduler;
+pub mod snapshot;
+pub mod statistics;
+pub mod trajectory;
 pub mod world;
\ No newline at end of file
@@ -0,0 +1,82 @@
+use crate::simulation::actors::behaviour::Inspection;
+use crate::simulation::replay::HumanFrame;
+use crate::simulation::world::{Element, World};
+use crate::types::Grid;
+
+use std::sync::{Arc, Mutex};
+
+/// Immutable picture of a world after a tick, handed from the simulation to
+/// whatever draws it.
+#[derive(Clone, Debug)]
+pub struct RenderFrame {
+    pub tick : u64,
+    /// Cells, shared with the previous frames while they do
+    /// not change.
+    pub cells : Arc<Grid<Element>>,
+    pub humans : Vec<HumanFrame>,
+    /// What the behaviour of each human makes of it, `None` once dead.
+    pub inspections : Vec<Option<Inspection>>
+}
+
+impl RenderFrame {
+    pub fn alive(&self) -> usize {
+        self.humans.iter().filter(|human| human.alive).count()
+    }
+}
+
+/// Double buffer between the simulation and the renderer : the simulation
+/// publishes its frames into the back slot, the renderer takes the latest one
+/// and keeps drawing it until a newer one is published. The lock only guards
+/// the swap of a pointer, never a step or a draw.
+#[derive(Default)]
+pub struct FrameBuffer {
+    back : Mutex<Option<Arc<RenderFrame>>>
+}
+
+impl FrameBuffer {
+    /// Publishes `frame`, replacing the previous one if it was never taken.
+    pub fn publish(&self, frame : Arc<RenderFrame>) {
+        *self.back.lock().unwrap() = Some(frame);
+    }
+
+    /// Frame published since the last call, if any.
+    pub fn take(&self) -> Option<Arc<RenderFrame>> {
+        self.back.lock().unwrap().take()
+    }
+}
+
+/// Publishes the frames of a world into a `FrameBuffer`, copying the cells
+/// only when the environment changed since the previous frame.
+pub struct FramePublisher {
+    buffer : Arc<FrameBuffer>,
+    cells : Option<(u64, Arc<Grid<Element>>)>
+}
+
+impl FramePublisher {
+    pub fn new(buffer : Arc<FrameBuffer>) -> FramePublisher {
+        FramePublisher { buffer, cells : None }
+    }
+
+    /// Builds the frame of `world` and publishes it.
+    pub fn publish(&mut self, world : &World) {
+        let cells = {
+            let environment = world.environment.read().unwrap();
+            match &self.cells {
+                Some((revision, cells)) if *revision == environment.revision => cells.clone(),
+                _ => {
+                    let cells = Arc::new(environment.cells.clone());
+                    self.cells = Some((environment.revision, cells.clone()));
+                    cells
+                }
+            }
+        };
+        self.buffer.publish(Arc::new(RenderFrame {
+            tick : world.tick,
+            cells,
+            humans : world.humans.iter().map(HumanFrame::new).collect(),
+            inspections : world.humans.iter()
+                .map(|human| if human.alive { human.behaviour.read().unwrap().inspect(human) } else { None })
+                .collect()
+        }));
+    }
+}
@@ -0,0 +1,273 @@
+use crate::simulation::actors::behaviour::WAIT;
+use crate::simulation::actors::humans::Human;
+use crate::simulation::world::{Element, World};
+use crate::types::{Grid, Position};
+
+use serde::{Deserialize, Serialize};
+
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter};
+use std::path::Path;
+
+/// Needs at or below this value are shown as pressing.
+pub const LOW_NEED : i32 = 30;
+
+/// What can be seen of a human at the end of a tick.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct HumanFrame {
+    pub position : Position,
+    pub age : u32,
+    pub hunger : i32,
+    pub thirst : i32,
+    pub energy : i32,
+    pub money : i32,
+    pub alive : bool,
+    /// Action index, `None` if the human did not act during the tick.
+    pub action : Option<u8>
+}
+
+impl HumanFrame {
+    pub fn new(human : &Human) -> HumanFrame {
+        HumanFrame {
+            position : human.position,
+            age : human.age,
+            hunger : human.hunger.value,
+            thirst : human.thirst.value,
+            energy : human.energy.value,
+            money : human.money.value,
+            alive : human.alive,
+            action : human.last_action.map(|action| action as u8)
+        }
+    }
+
+    pub fn thirsty(&self) -> bool {
+        self.alive && self.thirst <= LOW_NEED
+    }
+
+    pub fn hungry(&self) -> bool {
+        self.alive && self.hunger <= LOW_NEED
+    }
+
+    /// Waited during the tick.
+    pub fn resting(&self) -> bool {
+        self.alive && self.action == Some(WAIT as u8)
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TickRecord {
+    pub tick : u64,
+    /// Every human, in the order of `World::humans`.
+    pub humans : Vec<HumanFrame>,
+    /// Cells whose element changed during the tick, with their new element.
+    pub cells : Vec<(Position, Element)>,
+    /// Indices of the humans who died during the tick.
+    pub deaths : Vec<usize>
+}
+
+/// Recording of a run : the starting state and what changed at every tick.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Replay {
+    pub cell_size : usize,
+    pub start_tick : u64,
+    /// Cells when the recording started.
+    pub cells : Grid<Element>,
+    pub humans : Vec<HumanFrame>,
+    pub ticks : Vec<TickRecord>
+}
+
+impl Replay {
+    pub fn save<P : AsRef<Path>>(&self, path : P) -> io::Result<()> {
+        let writer = BufWriter::new(File::create(path)?);
+        bincode::serialize_into(writer, self).map_err(io::Error::other)
+    }
+
+    pub fn load<P : AsRef<Path>>(path : P) -> io::Result<Replay> {
+        let reader = BufReader::new(File::open(path)?);
+        bincode::deserialize_from(reader).map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+    }
+}
+
+/// Builds a `Replay` of a world, one `record` call after each step.
+pub struct Recorder {
+    replay : Replay,
+    // Cells as of the last record, to find the ones that changed.
+    cells : Grid<Element>,
+    // Revision of the environment as of the last record.
+    revision : u64
+}
+
+impl Recorder {
+    pub fn new(world : &World) -> Recorder {
+        let environment = world.environment.read().unwrap();
+        let cells = environment.cells.clone();
+        Recorder {
+            replay : Replay {
+                cell_size : world.cell_size,
+                start_tick : world.tick,
+                cells : cells.clone(),
+                humans : world.humans.iter().map(HumanFrame::new).collect(),
+                ticks : Vec::new()
+            },
+            cells,
+            revision : environment.revision
+        }
+    }
+
+    pub fn record(&mut self, world : &World) {
+        let mut changes = Vec::new();
+        {
+            // The cells are only scanned when the environment says they changed.
+            let environment = world.environment.read().unwrap();
+            if environment.revision != self.revision {
+                self.revision = environment.revision;
+                for (position, element) in environment.cells.iter() {
+                    if self.cells[position] != *element {
+                        changes.push((position, *element));
+                        self.cells[position] = *element;
+                    }
+                }
+            }
+        }
+
+        let previous = self.replay.ticks.last().map_or(&self.replay.humans, |record| &record.humans);
+        let humans : Vec<HumanFrame> = world.humans.iter().map(HumanFrame::new).collect();
+        let deaths = humans.iter()
+        .enumerate()
+        .filter(|(index, human)| !human.alive && previous.get(*index).is_some_and(|before| before.alive))
+        .map(|(index, _)| index)
+        .collect();
+
+        self.replay.ticks.push(TickRecord { tick : world.tick, humans, cells : changes, deaths });
+    }
+
+    pub fn replay(&self) -> &Replay {
+        &self.replay
+    }
+
+    pub fn finish(self) -> Replay {
+        self.replay
+    }
+}
+
+/// Plays a `Replay` back, frame by frame, without running any behaviour.
+/// Frame 0 is the starting state, frame `i` the state after the `i`th tick.
+pub struct ReplayPlayer {
+    replay : Replay,
+    frame : usize,
+    cells : Grid<Element>
+}
+
+impl ReplayPlayer {
+    pub fn new(replay : Replay) -> ReplayPlayer {
+        let cells = replay.cells.clone();
+        ReplayPlayer { replay, frame : 0, cells }
+    }
+
+    pub fn replay(&self) -> &Replay {
+        &self.replay
+    }
+
+    pub fn nb_frames(&self) -> usize {
+        self.replay.ticks.len() + 1
+    }
+
+    pub fn frame(&self) -> usize {
+        self.frame
+    }
+
+    pub fn tick(&self) -> u64 {
+        self.replay.start_tick + self.frame as u64
+    }
+
+    pub fn cells(&self) -> &Grid<Element> {
+        &self.cells
+    }
+
+    pub fn humans(&self) -> &[HumanFrame] {
+        match self.frame {
+            0 => &self.replay.humans,
+            frame => &self.replay.ticks[frame - 1].humans
+        }
+    }
+
+    /// Record of the tick that led to the current frame.
+    pub fn record(&self) -> Option<&TickRecord> {
+        self.frame.checked_sub(1).map(|tick| &self.replay.ticks[tick])
+    }
+
+    /// Moves to `frame`, clamped to the recording. Cell changes are applied
+    /// forward, going back replays them from the start.
+    pub fn seek(&mut self, frame : usize) {
+        let frame = frame.min(self.nb_frames() - 1);
+        if frame < self.frame {
+            self.cells.clone_from(&self.replay.cells);
+            self.frame = 0;
+        }
+        for record in &self.replay.ticks[self.frame..frame] {
+            for (position, element) in record.cells.iter() {
+                self.cells[*position] = *element;
+            }
+        }
+        self.frame = frame;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::actors::behaviour::SharedBehaviour;
+    use crate::simulation::actors::behaviour_tree::BTBehaviour;
+    use std::sync::{Arc, RwLock};
+
+    /// Thirsty human next to a small pond it drains.
+    fn draining_world() -> World {
+        let mut cells = Grid::new(4, 2, Element::Grass(1.0));
+        cells[Position::new(0, 0)] = Element::Water(1.0);
+        cells[Position::new(0, 1)] = Element::Water(1.0);
+        let mut world = World::from_cells(cells, 1);
+        world.environment.write().unwrap().depletion = 0.5;
+        let behaviour : SharedBehaviour = Arc::new(RwLock::new(BTBehaviour::survival()));
+        let mut human = Human::new(3, 0, behaviour, world.environment.clone());
+        human.thirst.value = 45;
+        world.add_human(human);
+        world
+    }
+
+    #[test]
+    fn seeking_reproduces_the_recorded_states() {
+        let mut world = draining_world();
+        let mut recorder = Recorder::new(&world);
+        let mut states = vec![(world.environment.read().unwrap().cells.clone(), HumanFrame::new(&world.humans[0]))];
+        for _ in 0..60 {
+            world.step_time();
+            recorder.record(&world);
+            states.push((world.environment.read().unwrap().cells.clone(), HumanFrame::new(&world.humans[0])));
+        }
+        // The pond ran dry during the run.
+        assert!(states.last().unwrap().0.values().all(|element| !matches!(element, Element::Water(_))));
+
+        let mut player = ReplayPlayer::new(recorder.finish());
+        assert_eq!(player.nb_frames(), states.len());
+        for frame in (0..states.len()).chain((0..states.len()).rev()).chain([7, 42, 3, 59]) {
+            player.seek(frame);
+            assert_eq!(player.frame(), frame);
+            assert!(*player.cells() == states[frame].0, "cells of frame {frame}");
+            assert_eq!(player.humans(), &[states[frame].1]);
+        }
+    }
+
+    #[test]
+    fn cells_are_only_scanned_when_the_revision_moves() {
+        let world = draining_world();
+        let mut recorder = Recorder::new(&world);
+        // Changed behind the back of the environment : not picked up.
+        world.environment.write().unwrap().cells[Position::new(2, 1)] = Element::Tree(1.0);
+        recorder.record(&world);
+        assert!(recorder.replay().ticks[0].cells.is_empty());
+
+        world.environment.write().unwrap().revision += 1;
+        recorder.record(&world);
+        assert_eq!(recorder.replay().ticks[1].cells, vec![(Position::new(2, 1), Element::Tree(1.0))]);
+    }
+}
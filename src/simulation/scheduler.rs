@@ -0,0 +1,298 @@
+use crate::simulation::world::World;
+
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// Bounds of the time step when speeding up or slowing down.
+pub const MIN_TIME_STEP : Duration = Duration::from_millis(1);
+pub const MAX_TIME_STEP : Duration = Duration::from_secs(5);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Command {
+    Pause,
+    Resume,
+    TogglePause,
+    /// Runs that many steps right away, then stays paused.
+    Step(u64),
+    /// Time between two steps, 0 runs as fast as possible.
+    SetTimeStep(Duration),
+    Shutdown
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SchedulerStatus {
+    pub paused : bool,
+    pub time_step : Duration,
+    pub tick : u64
+}
+
+/// Runs a world on its own thread at a fixed time step, under the control of
+/// `Command`s sent from the UI or the command line.
+///
+/// The world is shared behind a mutex, locked for the length of each step.
+pub struct Scheduler {
+    world : Arc<Mutex<World>>,
+    status : Arc<Mutex<SchedulerStatus>>,
+    commands : Sender<Command>,
+    thread : Option<JoinHandle<()>>
+}
+
+impl Scheduler {
+    /// Starts running `world`, calling `on_tick` after each step. Pauses by
+    /// itself when the world reaches tick `pause_at`, if any.
+    pub fn start<F>(world : World, time_step : Duration, paused : bool, pause_at : Option<u64>, on_tick : F) -> Scheduler
+    where F : FnMut(&World) + Send + 'static {
+        let status = Arc::new(Mutex::new(SchedulerStatus { paused, time_step, tick : world.tick }));
+        let world = Arc::new(Mutex::new(world));
+        let (commands, receiver) = mpsc::channel();
+
+        let mut simulation = Simulation {
+            world : world.clone(),
+            status : status.clone(),
+            commands : receiver,
+            pause_at,
+            on_tick
+        };
+        let thread = thread::spawn(move || simulation.run());
+
+        Scheduler { world, status, commands, thread : Some(thread) }
+    }
+
+    pub fn world(&self) -> Arc<Mutex<World>> {
+        self.world.clone()
+    }
+
+    pub fn status(&self) -> SchedulerStatus {
+        *self.status.lock().unwrap()
+    }
+
+    /// Sends `command` to the simulation thread. Ignored once it stopped.
+    pub fn send(&self, command : Command) {
+        let _ = self.commands.send(command);
+    }
+
+    pub fn pause(&self) {
+        self.send(Command::Pause);
+    }
+
+    pub fn resume(&self) {
+        self.send(Command::Resume);
+    }
+
+    pub fn toggle_pause(&self) {
+        self.send(Command::TogglePause);
+    }
+
+    pub fn step(&self, steps : u64) {
+        self.send(Command::Step(steps));
+    }
+
+    pub fn set_time_step(&self, time_step : Duration) {
+        self.send(Command::SetTimeStep(time_step));
+    }
+
+    /// Halves the time step, down to `MIN_TIME_STEP`.
+    pub fn speed_up(&self) {
+        self.set_time_step((self.status().time_step / 2).clamp(MIN_TIME_STEP, MAX_TIME_STEP));
+    }
+
+    /// Doubles the time step, up to `MAX_TIME_STEP`.
+    pub fn slow_down(&self) {
+        self.set_time_step((self.status().time_step * 2).clamp(MIN_TIME_STEP, MAX_TIME_STEP));
+    }
+
+    /// Stops the simulation thread after its current step and gives the
+    /// world back.
+    pub fn shutdown(self) -> World {
+        let world = self.world.clone();
+        drop(self);
+        match Arc::try_unwrap(world) {
+            Ok(world) => world.into_inner().unwrap(),
+            Err(_) => panic!("the world is still shared outside of the scheduler")
+        }
+    }
+
+    fn stop(&mut self) {
+        if let Some(thread) = self.thread.take() {
+            self.send(Command::Shutdown);
+            thread.join().unwrap();
+        }
+    }
+}
+
+impl Drop for Scheduler {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+struct Simulation<F> {
+    world : Arc<Mutex<World>>,
+    status : Arc<Mutex<SchedulerStatus>>,
+    commands : Receiver<Command>,
+    pause_at : Option<u64>,
+    on_tick : F
+}
+
+impl<F : FnMut(&World)> Simulation<F> {
+    fn step(&mut self) {
+        let mut world = self.world.lock().unwrap();
+        world.step_time();
+        (self.on_tick)(&world);
+        let mut status = self.status.lock().unwrap();
+        status.tick = world.tick;
+        if self.pause_at == Some(world.tick) {
+            status.paused = true;
+        }
+    }
+
+    /// Applies `command`, returns false on shutdown.
+    fn apply(&mut self, command : Command) -> bool {
+        match command {
+            Command::Pause => self.status.lock().unwrap().paused = true,
+            Command::Resume => self.status.lock().unwrap().paused = false,
+            Command::TogglePause => {
+                let mut status = self.status.lock().unwrap();
+                status.paused = !status.paused;
+            },
+            Command::Step(steps) => {
+                self.status.lock().unwrap().paused = true;
+                for _ in 0..steps {
+                    self.step();
+                }
+            },
+            Command::SetTimeStep(time_step) => self.status.lock().unwrap().time_step = time_step,
+            Command::Shutdown => return false
+        }
+        true
+    }
+
+    fn run(&mut self) {
+        let mut next_step = Instant::now();
+        loop {
+            let status = *self.status.lock().unwrap();
+            // Waits for the next step, or for a command while paused.
+            let command = if status.paused {
+                self.commands.recv().map_err(|_| RecvTimeoutError::Disconnected)
+            } else {
+                self.commands.recv_timeout(next_step.saturating_duration_since(Instant::now()))
+            };
+
+            match command {
+                Ok(command) => {
+                    if !self.apply(command) {
+                        return;
+                    }
+                    if status.paused {
+                        next_step = Instant::now();
+                    }
+                },
+                Err(RecvTimeoutError::Timeout) => {
+                    self.step();
+                    next_step += status.time_step;
+                    // Late steps are not caught up on.
+                    next_step = next_step.max(Instant::now());
+                },
+                Err(RecvTimeoutError::Disconnected) => return
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn start(time_step : Duration, paused : bool, pause_at : Option<u64>) -> Scheduler {
+        Scheduler::start(World::new(3, 3, 1), time_step, paused, pause_at, |_| ())
+    }
+
+    /// Waits for the simulation thread to get the scheduler in the expected
+    /// state, panics after a few seconds.
+    fn wait_for(scheduler : &Scheduler, expected : impl Fn(&SchedulerStatus) -> bool) -> SchedulerStatus {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        loop {
+            let status = scheduler.status();
+            if expected(&status) {
+                return status;
+            }
+            assert!(Instant::now() < deadline, "scheduler stuck at {status:?}");
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    #[test]
+    fn steps_run_right_away_then_stay_paused() {
+        let scheduler = start(MIN_TIME_STEP, true, None);
+        scheduler.step(3);
+        scheduler.step(2);
+        let status = wait_for(&scheduler, |status| status.tick == 5);
+        assert!(status.paused);
+        thread::sleep(Duration::from_millis(20));
+        assert_eq!(scheduler.status().tick, 5);
+        assert_eq!(scheduler.shutdown().tick, 5);
+    }
+
+    #[test]
+    fn stepping_a_running_world_pauses_it() {
+        let scheduler = start(MIN_TIME_STEP, false, None);
+        wait_for(&scheduler, |status| status.tick >= 1);
+        scheduler.step(1);
+        wait_for(&scheduler, |status| status.paused);
+        // Leaves the requested step time to land.
+        thread::sleep(Duration::from_millis(20));
+        let tick = scheduler.status().tick;
+        thread::sleep(Duration::from_millis(20));
+        assert_eq!(scheduler.status().tick, tick);
+    }
+
+    #[test]
+    fn pausing_stops_the_steps_until_resumed() {
+        let scheduler = start(MIN_TIME_STEP, false, None);
+        wait_for(&scheduler, |status| status.tick >= 3);
+        scheduler.pause();
+        let status = wait_for(&scheduler, |status| status.paused);
+        thread::sleep(Duration::from_millis(20));
+        assert_eq!(scheduler.status().tick, status.tick);
+
+        scheduler.resume();
+        wait_for(&scheduler, |next| !next.paused && next.tick > status.tick + 3);
+        scheduler.toggle_pause();
+        let status = wait_for(&scheduler, |status| status.paused);
+        scheduler.toggle_pause();
+        wait_for(&scheduler, |next| !next.paused && next.tick > status.tick);
+    }
+
+    #[test]
+    fn runs_pause_by_themselves_at_the_requested_tick() {
+        let scheduler = start(Duration::ZERO, false, Some(5));
+        wait_for(&scheduler, |status| status.paused);
+        thread::sleep(Duration::from_millis(20));
+        assert_eq!(scheduler.shutdown().tick, 5);
+    }
+
+    #[test]
+    fn speed_changes_stay_within_bounds() {
+        let scheduler = start(Duration::from_millis(4), true, None);
+        scheduler.speed_up();
+        wait_for(&scheduler, |status| status.time_step == Duration::from_millis(2));
+        for _ in 0..3 {
+            scheduler.speed_up();
+            wait_for(&scheduler, |status| status.time_step == MIN_TIME_STEP);
+        }
+
+        scheduler.set_time_step(Duration::from_secs(4));
+        wait_for(&scheduler, |status| status.time_step == Duration::from_secs(4));
+        for _ in 0..3 {
+            scheduler.slow_down();
+            wait_for(&scheduler, |status| status.time_step == MAX_TIME_STEP);
+        }
+
+        // Set directly, the time step is taken as is : 0 runs as fast as possible.
+        scheduler.set_time_step(Duration::ZERO);
+        scheduler.resume();
+        wait_for(&scheduler, |status| status.time_step == Duration::ZERO && status.tick >= 100);
+    }
+}
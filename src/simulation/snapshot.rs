@@ -0,0 +1,176 @@
+use crate::simulation::actors::behaviour::{PendingStep, SharedBehaviour};
+use crate::simulation::actors::humans::{Human, Need};
+use crate::simulation::actors::reward::{RewardBreakdown, RewardFunction};
+use crate::simulation::world::{Environment, World};
+use crate::types::{Position, SimRng};
+
+use serde::{Deserialize, Serialize};
+
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter};
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct HumanSnapshot {
+    pub position : Position,
+    pub age : u32,
+    pub hunger : Need,
+    pub thirst : Need,
+    pub energy : Need,
+    pub money : Need,
+    pub alive : bool,
+    /// Index into `WorldSnapshot::behaviours`.
+    pub behaviour : usize,
+    pub last_reward : RewardBreakdown,
+    pub rng : SimRng,
+    pub last_action : Option<usize>,
+    pub pending_step : Option<PendingStep>
+}
+
+/// Everything needed to resume a world exactly where it was captured.
+///
+/// Behaviours are code and are not part of the snapshot : only the state
+/// they change while running is, and restoring needs the same behaviours,
+/// in the order of `World::behaviours`.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct WorldSnapshot {
+    pub environment : Environment,
+    pub cell_size : usize,
+    pub rng : SimRng,
+    pub tick : u64,
+    pub humans : Vec<HumanSnapshot>,
+    /// State of each distinct behaviour, see `Behaviour::save_state`.
+    pub behaviours : Vec<Option<Vec<u8>>>
+}
+
+fn invalid_data<E : Into<Box<dyn std::error::Error + Send + Sync>>>(error : E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, error)
+}
+
+impl WorldSnapshot {
+    /// Captures `world` between two steps.
+    pub fn capture(world : &World) -> WorldSnapshot {
+        let behaviours = world.behaviours();
+        let humans = world.humans.iter()
+        .map(|human| HumanSnapshot {
+            position : human.position,
+            age : human.age,
+            hunger : human.hunger,
+            thirst : human.thirst,
+            energy : human.energy,
+            money : human.money,
+            alive : human.alive,
+            behaviour : behaviours.iter().position(|behaviour| Arc::ptr_eq(behaviour, &human.behaviour)).unwrap(),
+            last_reward : human.last_reward.clone(),
+            rng : human.rng.clone(),
+            last_action : human.last_action,
+            pending_step : human.pending_step
+        })
+        .collect();
+
+        WorldSnapshot {
+            environment : world.environment.read().unwrap().clone(),
+            cell_size : world.cell_size,
+            rng : world.rng.clone(),
+            tick : world.tick,
+            humans,
+            behaviours : behaviours.iter().map(|behaviour| behaviour.read().unwrap().save_state()).collect()
+        }
+    }
+
+    /// New world in the captured state. `behaviours` drive the humans and get
+    /// back their saved state, `reward` is given to every human.
+    pub fn restore(&self, behaviours : &[SharedBehaviour], reward : Arc<dyn RewardFunction>) -> io::Result<World> {
+        if behaviours.len() != self.behaviours.len() {
+            return Err(invalid_data(format!("snapshot has {} behaviours, {} given", self.behaviours.len(), behaviours.len())));
+        }
+        for (behaviour, state) in behaviours.iter().zip(self.behaviours.iter()) {
+            if let Some(state) = state {
+                behaviour.write().unwrap().restore_state(state)?;
+            }
+        }
+
+        let mut world = World::new(0, 0, self.cell_size);
+        world.environment = Arc::new(RwLock::new(self.environment.clone()));
+        world.rng = self.rng.clone();
+        world.tick = self.tick;
+        // Pushed directly, `add_human` would reseed their generators.
+        world.humans = self.humans.iter()
+        .map(|snapshot| {
+            let mut human = Human::new(snapshot.position.x, snapshot.position.y,
+                behaviours[snapshot.behaviour].clone(), world.environment.clone());
+            human.age = snapshot.age;
+            human.hunger = snapshot.hunger;
+            human.thirst = snapshot.thirst;
+            human.energy = snapshot.energy;
+            human.money = snapshot.money;
+            human.alive = snapshot.alive;
+            human.reward = reward.clone();
+            human.last_reward = snapshot.last_reward.clone();
+            human.rng = snapshot.rng.clone();
+            human.last_action = snapshot.last_action;
+            human.pending_step = snapshot.pending_step;
+            human
+        })
+        .collect();
+        Ok(world)
+    }
+
+    pub fn save<P : AsRef<Path>>(&self, path : P) -> io::Result<()> {
+        let writer = BufWriter::new(File::create(path)?);
+        bincode::serialize_into(writer, self).map_err(io::Error::other)
+    }
+
+    pub fn load<P : AsRef<Path>>(path : P) -> io::Result<WorldSnapshot> {
+        let reader = BufReader::new(File::open(path)?);
+        bincode::deserialize_from(reader).map_err(invalid_data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::actors::behaviour::DRINK;
+    use crate::simulation::actors::behaviour_tree::BTBehaviour;
+    use crate::simulation::actors::reward::RewardConfig;
+    use crate::simulation::world::Element;
+    use crate::types::Grid;
+
+    /// A thirsty human two cells away from water, driven by the survival tree.
+    fn thirsty_world() -> World {
+        let mut cells = Grid::new(5, 1, Element::Grass(1.0));
+        cells[Position::new(0, 0)] = Element::Water(1.0);
+        let mut world = World::from_cells(cells, 1);
+        let behaviour : SharedBehaviour = Arc::new(RwLock::new(BTBehaviour::survival()));
+        let mut human = Human::new(2, 0, behaviour, world.environment.clone());
+        human.thirst.value = 45;
+        world.add_human(human);
+        world
+    }
+
+    fn run(world : &mut World, ticks : usize) -> Vec<(Position, i32, Option<usize>)> {
+        (0..ticks).map(|_| {
+            world.step_time();
+            let human = &world.humans[0];
+            (human.position, human.thirst.value, human.last_action)
+        })
+        .collect()
+    }
+
+    #[test]
+    fn resumed_trees_carry_on_where_they_were() {
+        let mut world = thirsty_world();
+        // Walked to the water and drank once, the tree is in its drinking loop.
+        run(&mut world, 3);
+        assert!(world.humans[0].thirst.value < 95);
+        let bytes = bincode::serialize(&WorldSnapshot::capture(&world)).unwrap();
+        let expected = run(&mut world, 5);
+        assert_eq!(expected[0].2, Some(DRINK));
+
+        let snapshot : WorldSnapshot = bincode::deserialize(&bytes).unwrap();
+        let behaviours : Vec<SharedBehaviour> = vec![Arc::new(RwLock::new(BTBehaviour::survival()))];
+        let mut resumed = snapshot.restore(&behaviours, Arc::new(RewardConfig::default())).unwrap();
+        assert_eq!(run(&mut resumed, 5), expected);
+    }
+}
@@ -0,0 +1,171 @@
+use crate::simulation::actors::behaviour::{ACTION_NAMES, NB_ACTIONS};
+use crate::simulation::actors::humans::{Human, Need};
+use crate::simulation::events::{DeathCause, Event, EventKind};
+use crate::simulation::world::{Element, World};
+
+use serde::{Deserialize, Serialize};
+
+use std::collections::VecDeque;
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex};
+
+/// Spread of a need over the living humans, all 0 if nobody is alive.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct NeedStats {
+    pub mean : f64,
+    pub min : i32,
+    pub max : i32
+}
+
+impl NeedStats {
+    pub fn new<'a, I : Iterator<Item = &'a Need>>(needs : I) -> NeedStats {
+        let values : Vec<i32> = needs.map(|need| need.value).collect();
+        if values.is_empty() {
+            return NeedStats::default();
+        }
+        NeedStats {
+            mean : values.iter().sum::<i32>() as f64 / values.len() as f64,
+            min : *values.iter().min().unwrap(),
+            max : *values.iter().max().unwrap()
+        }
+    }
+}
+
+/// State of a world after a tick.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct TickStats {
+    pub tick : u64,
+    pub alive : usize,
+    pub hunger : NeedStats,
+    pub thirst : NeedStats,
+    pub energy : NeedStats,
+    /// Sum of the amounts left in water cells.
+    pub water : f64,
+    /// Sum of the amounts left in tree cells.
+    pub food : f64,
+    /// Deaths during the tick.
+    pub deaths_hunger : usize,
+    pub deaths_thirst : usize,
+    /// Mean reward of the humans who learnt online during the tick, `None`
+    /// if nobody did.
+    pub reward : Option<f64>,
+    /// Number of humans who took each action during the tick.
+    pub actions : [usize; NB_ACTIONS]
+}
+
+impl TickStats {
+    pub fn write_csv_header<W : Write>(writer : &mut W) -> io::Result<()> {
+        write!(writer, "tick,alive")?;
+        for need in ["hunger", "thirst", "energy"] {
+            write!(writer, ",{need}_mean,{need}_min,{need}_max")?;
+        }
+        write!(writer, ",water,food,deaths_hunger,deaths_thirst,reward")?;
+        for name in ACTION_NAMES {
+            write!(writer, ",{name}")?;
+        }
+        writeln!(writer)
+    }
+
+    pub fn write_csv_row<W : Write>(&self, writer : &mut W) -> io::Result<()> {
+        write!(writer, "{},{}", self.tick, self.alive)?;
+        for need in [&self.hunger, &self.thirst, &self.energy] {
+            write!(writer, ",{},{},{}", need.mean, need.min, need.max)?;
+        }
+        write!(writer, ",{},{},{},{},", self.water, self.food, self.deaths_hunger, self.deaths_thirst)?;
+        if let Some(reward) = self.reward {
+            write!(writer, "{reward}")?;
+        }
+        for count in self.actions {
+            write!(writer, ",{count}")?;
+        }
+        writeln!(writer)
+    }
+}
+
+#[derive(Default)]
+struct Deaths {
+    hunger : usize,
+    thirst : usize
+}
+
+/// Samples a world after each tick and keeps the recent samples.
+pub struct StatisticsCollector {
+    pub history : VecDeque<TickStats>,
+    /// Number of samples kept, every one if `None`.
+    pub max_history : Option<usize>,
+    // Counted from the events of the world since the last sample.
+    deaths : Arc<Mutex<Deaths>>
+}
+
+impl StatisticsCollector {
+    /// Collector subscribed to the events of `world`.
+    pub fn attach(world : &mut World, max_history : Option<usize>) -> StatisticsCollector {
+        let deaths = Arc::new(Mutex::new(Deaths::default()));
+        let counted = deaths.clone();
+        world.events.subscribe(move |event : &Event| {
+            if let EventKind::HumanDied { cause } = event.kind {
+                let mut deaths = counted.lock().unwrap();
+                match cause {
+                    DeathCause::Hunger => deaths.hunger += 1,
+                    DeathCause::Thirst => deaths.thirst += 1
+                }
+            }
+        });
+        StatisticsCollector { history : VecDeque::new(), max_history, deaths }
+    }
+
+    /// Records the state of `world`, to be called after each step.
+    pub fn sample(&mut self, world : &World) -> &TickStats {
+        let living : Vec<&Human> = world.humans.iter().filter(|human| human.alive).collect();
+        let (mut water, mut food) = (0.0, 0.0);
+        for element in world.environment.read().unwrap().cells.values() {
+            match element {
+                Element::Water(amount) => water += amount,
+                Element::Tree(amount) => food += amount,
+                _ => ()
+            }
+        }
+        let mut actions = [0; NB_ACTIONS];
+        for action in world.humans.iter().filter_map(|human| human.last_action) {
+            actions[action] += 1;
+        }
+        let rewards : Vec<f64> = world.humans.iter()
+            .filter(|human| !human.last_reward.terms.is_empty())
+            .map(|human| human.last_reward.total())
+            .collect();
+        let deaths = std::mem::take(&mut *self.deaths.lock().unwrap());
+
+        let stats = TickStats {
+            tick : world.tick,
+            alive : living.len(),
+            hunger : NeedStats::new(living.iter().map(|human| &human.hunger)),
+            thirst : NeedStats::new(living.iter().map(|human| &human.thirst)),
+            energy : NeedStats::new(living.iter().map(|human| &human.energy)),
+            water,
+            food,
+            deaths_hunger : deaths.hunger,
+            deaths_thirst : deaths.thirst,
+            reward : (!rewards.is_empty()).then(|| rewards.iter().sum::<f64>() / rewards.len() as f64),
+            actions
+        };
+
+        if self.max_history.is_some_and(|max| self.history.len() >= max) {
+            self.history.pop_front();
+        }
+        self.history.push_back(stats);
+        self.history.back().unwrap()
+    }
+
+    pub fn latest(&self) -> Option<&TickStats> {
+        self.history.back()
+    }
+
+    /// Writes the kept samples as CSV, with a header.
+    pub fn write_csv<W : Write>(&self, writer : &mut W) -> io::Result<()> {
+        TickStats::write_csv_header(writer)?;
+        for stats in self.history.iter() {
+            stats.write_csv_row(writer)?;
+        }
+        Ok(())
+    }
+}
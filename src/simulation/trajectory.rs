@@ -0,0 +1,167 @@
+use crate::learning::qlearning::Agent;
+use crate::simulation::actors::behaviour::{Behaviour, MOVE_UP};
+use crate::simulation::actors::humans::Human;
+use crate::simulation::world::World;
+use crate::types::Position;
+
+use serde::Serialize;
+
+use std::collections::VecDeque;
+use std::io::{self, Write};
+
+/// Path of a human over one simulated life.
+#[derive(Clone, Debug, Serialize)]
+pub struct Trajectory {
+    pub behaviour : String,
+    pub episode : usize,
+    /// Cells `[x, y]` from the start of the life, one per step.
+    pub points : Vec<[i32; 2]>,
+    /// Moves into the edge of the world, which leave the human in place.
+    pub blocked : usize,
+    /// Steps going back to the cell of two steps before, from a third cell.
+    pub oscillations : usize,
+    pub distinct_cells : usize
+}
+
+impl Trajectory {
+    fn new(behaviour : &str, episode : usize, start : Position) -> Trajectory {
+        Trajectory {
+            behaviour : behaviour.to_string(),
+            episode,
+            points : vec![[start.x, start.y]],
+            blocked : 0,
+            oscillations : 0,
+            distinct_cells : 0
+        }
+    }
+
+    fn push(&mut self, position : Position, moved : bool) {
+        let point = [position.x, position.y];
+        let last = *self.points.last().unwrap();
+        if moved && point == last {
+            self.blocked += 1;
+        }
+        let before_last = self.points.len().checked_sub(2).map(|index| self.points[index]);
+        if point != last && before_last == Some(point) {
+            self.oscillations += 1;
+        }
+        self.points.push(point);
+    }
+
+    fn finish(mut self) -> Trajectory {
+        let mut cells = self.points.clone();
+        cells.sort_unstable();
+        cells.dedup();
+        self.distinct_cells = cells.len();
+        self
+    }
+
+    /// Writes `trajectories` as JSON, one trajectory per line.
+    pub fn write_json_lines<W : Write>(trajectories : &[Trajectory], writer : &mut W) -> io::Result<()> {
+        for trajectory in trajectories {
+            serde_json::to_writer(&mut *writer, trajectory)?;
+            writeln!(writer)?;
+        }
+        Ok(())
+    }
+}
+
+/// Follows `episodes` simulated lives of `human` driven by `behaviour`, the
+/// same lives as an evaluation with the same seed.
+pub fn trace(name : &str, behaviour : &dyn Behaviour, human : &mut Human, episodes : usize) -> Vec<Trajectory> {
+    let mut trajectories = Vec::new();
+    for episode in 0..episodes {
+        human.reset();
+        behaviour.reset();
+        let mut trajectory = Trajectory::new(name, episode, human.position);
+        let mut finished = false;
+        while !finished {
+            let action = behaviour.predict_action(human);
+            let (_, _, dead) = human.simulate_action(action);
+            finished = dead || human.truncated();
+            trajectory.push(human.position, action <= MOVE_UP);
+        }
+        trajectories.push(trajectory.finish());
+    }
+    trajectories
+}
+
+/// Last positions of every human of a live world, for trails. A trail starts
+/// over when its human dies or jumps by more than a cell.
+pub struct Trails {
+    pub length : usize,
+    trails : Vec<VecDeque<Position>>
+}
+
+impl Trails {
+    pub fn new(length : usize) -> Trails {
+        Trails { length, trails : Vec::new() }
+    }
+
+    /// Records the positions of the humans of `world`, after each step.
+    pub fn record(&mut self, world : &World) {
+        self.trails.resize_with(world.humans.len(), VecDeque::new);
+        for (trail, human) in self.trails.iter_mut().zip(world.humans.iter()) {
+            let jumped = trail.back().is_some_and(|last| (last.x - human.position.x).abs() + (last.y - human.position.y).abs() > 1);
+            if !human.alive || jumped {
+                trail.clear();
+            }
+            if human.alive {
+                trail.push_back(human.position);
+            }
+            while trail.len() > self.length {
+                trail.pop_front();
+            }
+        }
+    }
+
+    /// Forgets the trail of the human `index`, taken out of the world, so the
+    /// next ones keep theirs.
+    pub fn remove(&mut self, index : usize) {
+        if index < self.trails.len() {
+            self.trails.remove(index);
+        }
+    }
+
+    /// Positions of each human, oldest first.
+    pub fn trails(&self) -> &[VecDeque<Position>] {
+        &self.trails
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::actors::behaviour::{QLBehaviour, SharedBehaviour};
+    use crate::simulation::events::{Event, EventKind};
+    use crate::simulation::world::Element;
+    use crate::types::Grid;
+
+    use std::sync::{Arc, Mutex, RwLock};
+
+    #[test]
+    fn trails_follow_the_humans_left() {
+        let mut world = World::from_cells(Grid::new(4, 4, Element::Grass(1.0)), 1);
+        let behaviour : SharedBehaviour = Arc::new(RwLock::new(QLBehaviour::new()));
+        for x in 0..3 {
+            let human = Human::new(x, 0, behaviour.clone(), world.environment.clone());
+            world.add_human(human);
+        }
+        let trails = Arc::new(Mutex::new(Trails::new(5)));
+        let removals = trails.clone();
+        world.events.subscribe(move |event : &Event| if let EventKind::HumanRemoved { .. } = event.kind {
+            removals.lock().unwrap().remove(event.human);
+        });
+
+        trails.lock().unwrap().record(&world);
+        world.humans[2].position = Position::new(2, 1);
+        trails.lock().unwrap().record(&world);
+        world.remove_human(1);
+        trails.lock().unwrap().record(&world);
+
+        let trails = trails.lock().unwrap();
+        assert_eq!(trails.trails().len(), 2);
+        assert_eq!(trails.trails()[0], [Position::new(0, 0); 3]);
+        assert_eq!(trails.trails()[1], [Position::new(2, 0), Position::new(2, 1), Position::new(2, 1)]);
+    }
+}
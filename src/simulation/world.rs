@@ -1,8 +1,12 @@
-use crate::types::Position;
+use crate::types::{rng_from_seed, Grid, Position, SimRng};
+use crate::simulation::actors::behaviour::SharedBehaviour;
 use crate::simulation::actors::humans::Human;
-use std::{cell::RefCell, cmp::{max, min}, ops::DerefMut, rc::Rc, sync::{Arc, Mutex, RwLock}};
+use crate::simulation::events::{Event, EventBus, EventKind};
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::{ops::DerefMut, sync::{Arc, RwLock}};
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub enum Element {
     None, 
     Tree(f64), 
@@ -11,99 +15,264 @@ pub enum Element {
     House(f64)
 }
 
+/// Consumable resource kinds, and the element that provides them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Resource {
+    Water,
+    Food
+}
+
+impl Resource {
+    pub fn element(&self) -> Element {
+        match self {
+            Resource::Water => Element::Water(1.0),
+            Resource::Food => Element::Tree(1.0)
+        }
+    }
+}
+
 pub struct World {
     pub humans : Vec<Human>,
     pub environment : Arc<RwLock<Environment>>,
-    pub cell_size : usize
+    pub cell_size : usize,
+    /// Seeds the generators of the humans added to the world.
+    pub rng : SimRng,
+    /// Number of steps run so far.
+    pub tick : u64,
+    pub events : EventBus
 }
 
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Environment {
-    pub cells : Vec<Vec<Element>>,
-    pub world_limits : (usize, usize),
+    pub cells : Grid<Element>,
     pub forests : Vec<Position>,
-    pub lakes : Vec<Position>
+    pub lakes : Vec<Position>,
+    /// Amount taken out of a water or tree cell each time it is consumed.
+    /// Cells are emptied once their amount runs out, 0 keeps them forever.
+    pub depletion : f64,
+    /// Bumped each time the cells change through the methods of the
+    /// environment, so renderers know when to copy them again.
+    pub revision : u64
 }
 
 impl Environment {
-    pub fn get_element(&self, x : usize, y : usize) -> &Element {
-        &self.cells[x][y]
+    /// Replaces every cell, then rebuilds the regions.
+    pub fn set_cells(&mut self, cells : Grid<Element>) {
+        self.cells = cells;
+        self.revision += 1;
+        self.update_regions();
     }
 
-    pub fn distance_to_lake(&self, human : &Human) -> i32 {
+    /// Takes one serving out of the resource at `position`. Returns whether
+    /// the cell ran out, the regions are then rebuilt without it.
+    pub fn consume(&mut self, position : Position) -> bool {
+        if self.depletion <= 0.0 {
+            return false;
+        }
+        let cell = &mut self.cells[position];
+        if let Element::Water(amount) | Element::Tree(amount) = cell {
+            *amount -= self.depletion;
+            self.revision += 1;
+            if *amount <= 0.0 {
+                *cell = Element::None;
+                self.update_regions();
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Sets every cell of the square of side `2 * radius + 1` centred on
+    /// `centre` to `element`, then rebuilds the regions.
+    pub fn paint(&mut self, centre : Position, radius : usize, element : Element) {
+        let radius = radius as i32;
+        self.cells.fill_region(centre - Position::new(radius, radius), centre + Position::new(radius + 1, radius + 1), element);
+        self.revision += 1;
+        self.update_regions();
+    }
+
+    /// Rebuilds `forests` and `lakes` from the cells : one entry per patch of
+    /// connected trees or water, at the cell of the patch closest to its centre.
+    pub fn update_regions(&mut self) {
+        let mut visited = Grid::new(self.cells.width(), self.cells.height(), false);
+        self.forests.clear();
+        self.lakes.clear();
+
+        for (position, element) in self.cells.iter() {
+            let is_lake = match element {
+                Element::Water(_) => true,
+                Element::Tree(_) => false,
+                _ => continue
+            };
+            if visited[position] {
+                continue;
+            }
+
+            let same = |element : &Element| if is_lake { matches!(element, Element::Water(_)) } else { matches!(element, Element::Tree(_)) };
+            let mut patch = Vec::new();
+            let mut to_visit = vec![position];
+            visited[position] = true;
+            while let Some(p) = to_visit.pop() {
+                patch.push(p);
+                for next in self.cells.neighbours(p) {
+                    if !visited[next] && same(&self.cells[next]) {
+                        visited[next] = true;
+                        to_visit.push(next);
+                    }
+                }
+            }
+
+            let sum = patch.iter().fold(Position::new(0, 0), |sum, p| sum + *p);
+            let centre = Position::new(sum.x / patch.len() as i32, sum.y / patch.len() as i32);
+            let region = *patch.iter().min_by_key(|p| p.manhattan_dist(&centre)).unwrap();
+            if is_lake { self.lakes.push(region) } else { self.forests.push(region) }
+        }
+    }
+
+    /// Distance to the closest lake, `None` if there is no water.
+    pub fn distance_to_lake(&self, human : &Human) -> Option<i32> {
         self.lakes
         .iter()
         .map(|x| x.manhattan_dist(&human.position))
         .min()
-        .unwrap()
     }
 
-    pub fn distance_to_forest(&self, human : &Human) -> i32 {
+    /// Distance to the closest forest, `None` if there are no trees.
+    pub fn distance_to_forest(&self, human : &Human) -> Option<i32> {
         self.forests.iter()
         .map(|x| x.manhattan_dist(&human.position))
         .min()
-        .unwrap()
     }
 
-    pub fn closest_lake(&self, human : &Human) -> &Position {
+    pub fn closest_lake(&self, human : &Human) -> Option<&Position> {
+        self.closest_lake_to(human.position)
+    }
+
+    pub fn closest_forest(&self, human : &Human) -> Option<&Position> {
+        self.closest_forest_to(human.position)
+    }
+
+    /// Region of the closest lake, `None` if there is no water.
+    pub fn closest_lake_to(&self, position : Position) -> Option<&Position> {
         self.lakes
         .iter()
-        .min_by(|a,b| a.manhattan_dist(&human.position).cmp(&b.manhattan_dist(&human.position)))
-        .unwrap()
-    } 
+        .min_by(|a,b| a.manhattan_dist(&position).cmp(&b.manhattan_dist(&position)))
+    }
 
-    pub fn closest_forest(&self, human : &Human) -> &Position {
+    /// Region of the closest forest, `None` if there are no trees.
+    pub fn closest_forest_to(&self, position : Position) -> Option<&Position> {
         self.forests
         .iter()
-        .min_by(|a,b| a.manhattan_dist(&human.position).cmp(&b.manhattan_dist(&human.position)))
-        .unwrap()
-    } 
+        .min_by(|a,b| a.manhattan_dist(&position).cmp(&b.manhattan_dist(&position)))
+    }
 }
 
 impl World {
-    pub fn new(height : usize, width : usize, cell_size : usize) -> Self {
+    pub fn new(width : usize, height : usize, cell_size : usize) -> Self {
         World{
             humans : Vec::new(),
             environment : Arc::new(RwLock::new(Environment{
-                cells : vec![vec![Element::None; width]; height],
-                world_limits : (height, width),
+                cells : Grid::new(width, height, Element::None),
                 forests : Vec::new(),
-                lakes : Vec::new()
+                lakes : Vec::new(),
+                depletion : 0.0,
+                revision : 0
             })),
-            cell_size
+            cell_size,
+            rng : rng_from_seed(None),
+            tick : 0,
+            events : EventBus::default()
         }
     }
 
-    pub fn add_human(&mut self, human : Human) {
+    /// World over the given cells.
+    pub fn from_cells(cells : Grid<Element>, cell_size : usize) -> Self {
+        let world = World::new(cells.width(), cells.height(), cell_size);
+        world.environment.write().unwrap().set_cells(cells);
+        world
+    }
+
+    /// Adds `human`, reseeding its generator from the one of the world.
+    pub fn add_human(&mut self, mut human : Human) {
+        human.rng = SimRng::seed_from_u64(self.rng.gen());
+        let event = Event { tick : self.tick, human : self.humans.len(), kind : EventKind::HumanAdded { position : human.position } };
         self.humans.push(human);
+        self.events.publish(&event);
+    }
+
+    /// Takes the human `index` out of the world, the next ones move down by one.
+    pub fn remove_human(&mut self, index : usize) -> Human {
+        let human = self.humans.remove(index);
+        self.events.publish(&Event { tick : self.tick, human : index, kind : EventKind::HumanRemoved { position : human.position } });
+        human
     }
 
     fn set_cell(environment : &mut Environment, start : Position, stop : Position, val : Element) {
-        for x in max(start.x as usize, 0)
-        ..min(stop.x as usize, environment.cells.len()) {
-            for y in max(start.y as usize, 0)
-            ..min(stop.y as usize, environment.cells[x].len()) {
-                environment.cells[x][y] = val;
-            }
-        }
+        environment.cells.fill_region(start, stop, val);
+        environment.revision += 1;
     }
 
     pub fn add_forest(&mut self, start : Position, stop : Position) {
         let mut environment = self.environment.write().unwrap();
         World::set_cell(environment.deref_mut(), start, stop, Element::Tree(1.0));
-        let mid = Position{ x : (start.x + stop.x)/2, y : (start.y + stop.y)};
+        let mid = Position{ x : (start.x + stop.x)/2, y : (start.y + stop.y)/2};
         environment.forests.push(mid);
     }
 
     pub fn add_lake(&mut self, start : Position, stop : Position) {
         let mut environment = self.environment.write().unwrap();
         World::set_cell(environment.deref_mut(), start, stop, Element::Water(1.0));
-        let mid = Position{ x : (start.x + stop.x)/2, y : (start.y + stop.y)};
+        let mid = Position{ x : (start.x + stop.x)/2, y : (start.y + stop.y)/2};
         environment.lakes.push(mid);
     }
 
+    /// Distinct behaviours of the humans, in the order of the first human
+    /// driven by each.
+    pub fn behaviours(&self) -> Vec<SharedBehaviour> {
+        let mut behaviours : Vec<SharedBehaviour> = Vec::new();
+        for human in self.humans.iter() {
+            if !behaviours.iter().any(|behaviour| Arc::ptr_eq(behaviour, &human.behaviour)) {
+                behaviours.push(human.behaviour.clone());
+            }
+        }
+        behaviours
+    }
+
+    /// Steps every human, then publishes the events of the step, numbered
+    /// with the tick being run.
     pub fn step_time(&mut self) {
-        for human in self.humans.iter_mut() {
+        for (index, human) in self.humans.iter_mut().enumerate() {
             human.step_time();
+            for kind in human.events.drain(..) {
+                self.events.publish(&Event { tick : self.tick + 1, human : index, kind });
+            }
+        }
+
+        // Each shared behaviour learns once per tick.
+        for behaviour in self.behaviours() {
+            behaviour.write().unwrap().learn();
         }
+        self.tick += 1;
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn depleted_cells_leave_their_region() {
+        let mut cells = Grid::new(3, 1, Element::Grass(1.0));
+        cells[Position::new(0, 0)] = Element::Water(1.0);
+        cells[Position::new(2, 0)] = Element::Water(1.0);
+        let world = World::from_cells(cells, 1);
+        let mut environment = world.environment.write().unwrap();
+        environment.depletion = 0.6;
+        assert_eq!(environment.lakes.len(), 2);
+
+        assert!(!environment.consume(Position::new(0, 0)));
+        assert_eq!(environment.lakes.len(), 2);
+        assert!(environment.consume(Position::new(0, 0)));
+        assert_eq!(environment.lakes, vec![Position::new(2, 0)]);
+        assert_eq!(environment.closest_lake_to(Position::new(0, 0)), Some(&Position::new(2, 0)));
+    }
+}
@@ -1,6 +1,20 @@
-use std::ops::{Add, Sub};
+use rand::{FromEntropy, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::ops::{Add, Index, IndexMut, Sub};
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+/// Random number generator of the simulation, seedable and serializable so
+/// that runs can be reproduced.
+pub type SimRng = rand_pcg::Pcg64Mcg;
+
+/// Generator seeded with `seed`, or from the system entropy if none.
+pub fn rng_from_seed(seed : Option<u64>) -> SimRng {
+    match seed {
+        Some(seed) => SimRng::seed_from_u64(seed),
+        None => SimRng::from_entropy()
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct Position {
     /// The x coordinate.
     pub x: i32,
@@ -34,3 +48,230 @@ impl Sub<Position> for Position {
         Position{x : self.x - rhs.x, y : self.y - rhs.y}
     }
 }
+
+/// Cells of a rectangular world indexed by `Position`, `x` from 0 to `width`
+/// excluded and `y` from 0 to `height` excluded. Stored flat, column after
+/// column, so iterating goes through `x` then `y` as nested columns did.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "RawGrid<T>")]
+pub struct Grid<T> {
+    width : usize,
+    height : usize,
+    cells : Vec<T>
+}
+
+/// Grid as read, before its size is checked against its cells.
+#[derive(Deserialize)]
+struct RawGrid<T> {
+    width : usize,
+    height : usize,
+    cells : Vec<T>
+}
+
+impl<T> TryFrom<RawGrid<T>> for Grid<T> {
+    type Error = String;
+
+    fn try_from(raw : RawGrid<T>) -> Result<Grid<T>, String> {
+        if raw.width.checked_mul(raw.height) != Some(raw.cells.len()) {
+            return Err(format!("{}x{} grid with {} cells", raw.width, raw.height, raw.cells.len()));
+        }
+        Ok(Grid { width : raw.width, height : raw.height, cells : raw.cells })
+    }
+}
+
+impl<T : Clone> Grid<T> {
+    pub fn new(width : usize, height : usize, value : T) -> Grid<T> {
+        Grid { width, height, cells : vec![value; width * height] }
+    }
+
+    /// Sets every cell from `from` included to `to` excluded to `value`,
+    /// leaving out what falls outside the grid.
+    pub fn fill_region(&mut self, from : Position, to : Position, value : T) {
+        for position in self.region(from, to).positions() {
+            self[position] = value.clone();
+        }
+    }
+}
+
+impl<T> Grid<T> {
+    /// Grid whose cells are `f` of their position.
+    pub fn from_fn<F : FnMut(Position) -> T>(width : usize, height : usize, mut f : F) -> Grid<T> {
+        let cells = (0..width * height).map(|index| f(Position::new((index / height) as i32, (index % height) as i32))).collect();
+        Grid { width, height, cells }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn contains(&self, position : Position) -> bool {
+        position.x >= 0 && position.y >= 0 && (position.x as usize) < self.width && (position.y as usize) < self.height
+    }
+
+    /// Index of `position` in the flat storage, also a unique number for each
+    /// cell.
+    pub fn index(&self, position : Position) -> Option<usize> {
+        self.contains(position).then(|| position.x as usize * self.height + position.y as usize)
+    }
+
+    pub fn get(&self, position : Position) -> Option<&T> {
+        self.index(position).map(|index| &self.cells[index])
+    }
+
+    pub fn get_mut(&mut self, position : Position) -> Option<&mut T> {
+        self.index(position).map(|index| &mut self.cells[index])
+    }
+
+    /// Closest position of the grid to `position`, the origin if the grid is
+    /// empty.
+    pub fn clamp(&self, position : Position) -> Position {
+        Position::new(position.x.clamp(0, (self.width as i32 - 1).max(0)), position.y.clamp(0, (self.height as i32 - 1).max(0)))
+    }
+
+    /// Every position, column after column.
+    pub fn positions(&self) -> impl Iterator<Item = Position> {
+        let height = self.height;
+        (0..self.width * height).map(move |index| Position::new((index / height) as i32, (index % height) as i32))
+    }
+
+    /// Every cell with its position, column after column.
+    pub fn iter(&self) -> impl Iterator<Item = (Position, &T)> {
+        self.positions().zip(self.cells.iter())
+    }
+
+    /// Every cell, column after column.
+    pub fn values(&self) -> std::slice::Iter<'_, T> {
+        self.cells.iter()
+    }
+
+    /// Positions next to `position` in the grid : right, left, below and above.
+    pub fn neighbours(&self, position : Position) -> impl Iterator<Item = Position> + '_ {
+        [Position::new(1, 0), Position::new(-1, 0), Position::new(0, 1), Position::new(0, -1)].into_iter()
+            .map(move |step| position + step)
+            .filter(|next| self.contains(*next))
+    }
+
+    /// View of the cells from `from` included to `to` excluded, clipped to the
+    /// grid.
+    pub fn region(&self, from : Position, to : Position) -> Region<'_, T> {
+        let from = Position::new(from.x.max(0), from.y.max(0));
+        let to = Position::new(to.x.min(self.width as i32).max(from.x), to.y.min(self.height as i32).max(from.y));
+        Region { grid : self, from, to }
+    }
+
+    /// Grid of `f` of every cell.
+    pub fn map<U, F : FnMut(&T) -> U>(&self, f : F) -> Grid<U> {
+        Grid { width : self.width, height : self.height, cells : self.cells.iter().map(f).collect() }
+    }
+}
+
+impl<T> Index<Position> for Grid<T> {
+    type Output = T;
+
+    fn index(&self, position : Position) -> &T {
+        match self.get(position) {
+            Some(cell) => cell,
+            None => panic!("{:?} is out of a {}x{} grid", position, self.width, self.height)
+        }
+    }
+}
+
+impl<T> IndexMut<Position> for Grid<T> {
+    fn index_mut(&mut self, position : Position) -> &mut T {
+        let (width, height) = (self.width, self.height);
+        match self.get_mut(position) {
+            Some(cell) => cell,
+            None => panic!("{:?} is out of a {}x{} grid", position, width, height)
+        }
+    }
+}
+
+/// Rectangle of cells of a `Grid`, see `Grid::region`.
+pub struct Region<'a, T> {
+    grid : &'a Grid<T>,
+    from : Position,
+    to : Position
+}
+
+impl<'a, T> Region<'a, T> {
+    /// Positions of the region, column after column.
+    pub fn positions(&self) -> impl Iterator<Item = Position> {
+        let (from, to) = (self.from, self.to);
+        (from.x..to.x).flat_map(move |x| (from.y..to.y).map(move |y| Position::new(x, y)))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Position, &'a T)> {
+        let grid = self.grid;
+        self.positions().map(move |position| (position, &grid[position]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn numbered(width : usize, height : usize) -> Grid<usize> {
+        Grid::from_fn(width, height, |position| position.x as usize * 10 + position.y as usize)
+    }
+
+    #[test]
+    fn get_and_index_are_bounds_checked() {
+        let grid = numbered(3, 2);
+        assert_eq!(grid.get(Position::new(2, 1)), Some(&21));
+        assert_eq!(grid[Position::new(1, 0)], 10);
+        assert_eq!(grid.get(Position::new(3, 0)), None);
+        assert_eq!(grid.get(Position::new(0, 2)), None);
+        assert_eq!(grid.get(Position::new(-1, 0)), None);
+        assert_eq!(grid.index(Position::new(0, 0)), Some(0));
+        assert_eq!(grid.index(Position::new(1, 1)), Some(3));
+        assert_eq!(grid.index(Position::new(0, -1)), None);
+    }
+
+    #[test]
+    #[should_panic]
+    fn indexing_outside_panics() {
+        let _ = numbered(3, 2)[Position::new(0, 2)];
+    }
+
+    #[test]
+    fn neighbours_stay_in_the_grid() {
+        let grid = numbered(3, 2);
+        let corner : Vec<Position> = grid.neighbours(Position::new(0, 0)).collect();
+        assert_eq!(corner, vec![Position::new(1, 0), Position::new(0, 1)]);
+        let edge : Vec<Position> = grid.neighbours(Position::new(1, 1)).collect();
+        assert_eq!(edge, vec![Position::new(2, 1), Position::new(0, 1), Position::new(1, 0)]);
+    }
+
+    #[test]
+    fn regions_are_clipped() {
+        let grid = numbered(3, 2);
+        let region : Vec<Position> = grid.region(Position::new(-2, 1), Position::new(2, 5)).positions().collect();
+        assert_eq!(region, vec![Position::new(0, 1), Position::new(1, 1)]);
+        assert_eq!(grid.region(Position::new(2, 0), Position::new(1, 2)).positions().count(), 0);
+        assert_eq!(grid.region(Position::new(5, 5), Position::new(8, 8)).positions().count(), 0);
+
+        let mut filled = Grid::new(3, 2, 0);
+        filled.fill_region(Position::new(-1, -1), Position::new(2, 1), 1);
+        assert_eq!(filled.values().sum::<i32>(), 2);
+    }
+
+    #[test]
+    fn clamp_keeps_positions_in_the_grid() {
+        let grid = numbered(3, 2);
+        assert_eq!(grid.clamp(Position::new(-1, 5)), Position::new(0, 1));
+        assert_eq!(grid.clamp(Position::new(1, 1)), Position::new(1, 1));
+        assert_eq!(Grid::new(0, 0, 0).clamp(Position::new(4, -4)), Position::new(0, 0));
+    }
+
+    #[test]
+    fn deserializing_checks_the_size() {
+        let grid = numbered(3, 2);
+        let text = serde_json::to_string(&grid).unwrap();
+        assert_eq!(serde_json::from_str::<Grid<usize>>(&text).unwrap(), grid);
+        assert!(serde_json::from_str::<Grid<usize>>(r#"{"width":3,"height":2,"cells":[0,1,2]}"#).is_err());
+    }
+}